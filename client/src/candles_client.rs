@@ -0,0 +1,274 @@
+use std::{future::Future, time::Duration};
+
+use service_candle_writer_generated_proto::candles_grpc::candles_service_client::CandlesServiceClient;
+use service_candle_writer_generated_proto::{
+    CandleTypeGrpc, GetCandlesRequest, GetLastCandlesRequest, GetSpreadCandlesRequest,
+    ListInstrumentsRequest, PriceSideGrpc,
+};
+use tokio::sync::Mutex;
+use tonic::{transport::Channel, transport::Endpoint, Code, Request, Status};
+
+use crate::{
+    CandleModel, CandleType, CandlesHistory, InstrumentInfo, InstrumentLastCandles, PriceSide,
+    SpreadCandleModel,
+};
+
+#[derive(Debug)]
+pub enum CandlesClientError {
+    Connect(tonic::transport::Error),
+    Status(Status),
+}
+
+impl CandlesClientError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            CandlesClientError::Connect(_) => true,
+            CandlesClientError::Status(status) => status.code() == Code::Unavailable,
+        }
+    }
+}
+
+impl std::fmt::Display for CandlesClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CandlesClientError::Connect(err) => {
+                write!(f, "Can't connect to candles service: {}", err)
+            }
+            CandlesClientError::Status(status) => write!(f, "Candles service error: {}", status),
+        }
+    }
+}
+
+impl std::error::Error for CandlesClientError {}
+
+#[derive(Debug, Clone)]
+struct CandlesClientSettings {
+    url: String,
+    timeout: Duration,
+    connect_timeout: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_retries: usize,
+}
+
+pub struct CandlesClientBuilder {
+    settings: CandlesClientSettings,
+}
+
+impl CandlesClientBuilder {
+    pub fn new(url: String) -> Self {
+        Self {
+            settings: CandlesClientSettings {
+                url,
+                timeout: Duration::from_secs(10),
+                connect_timeout: Duration::from_secs(5),
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(5),
+                max_retries: 5,
+            },
+        }
+    }
+
+    /// Deadline of every request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = timeout;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.settings.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Delay before the first reconnect, doubled on every next attempt up to `max_backoff`
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.settings.initial_backoff = initial_backoff;
+        self.settings.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.settings.max_retries = max_retries;
+        self
+    }
+
+    /// The connection is established on the first request
+    pub fn build(self) -> CandlesClient {
+        CandlesClient {
+            settings: self.settings,
+            client: Mutex::new(None),
+        }
+    }
+}
+
+pub struct CandlesClient {
+    settings: CandlesClientSettings,
+    client: Mutex<Option<CandlesServiceClient<Channel>>>,
+}
+
+impl CandlesClient {
+    /// Candles for the [from, to) range, dates are unix timestamps in seconds.
+    /// With `fill_gaps` the periods without ticks come as flat synthetic candles.
+    /// The candles come with the price precision of the instrument.
+    pub async fn get_candles(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+        from: u64,
+        to: u64,
+        fill_gaps: bool,
+    ) -> Result<CandlesHistory, CandlesClientError> {
+        let request = GetCandlesRequest {
+            instrument: instrument.to_string(),
+            candle_type: CandleTypeGrpc::from(candle_type) as i32,
+            side: PriceSideGrpc::from(side) as i32,
+            from,
+            to,
+            fill_gaps,
+        };
+
+        let response = self
+            .execute(|mut client, timeout| {
+                let request = with_timeout(request.clone(), timeout);
+                async move { client.get_candles(request).await }
+            })
+            .await?;
+
+        Ok(response.into())
+    }
+
+    /// Spread candles for the [from, to) range, dates are unix timestamps in seconds
+    pub async fn get_spread_candles(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<SpreadCandleModel>, CandlesClientError> {
+        let request = GetSpreadCandlesRequest {
+            instrument: instrument.to_string(),
+            candle_type: CandleTypeGrpc::from(candle_type) as i32,
+            from,
+            to,
+        };
+
+        let response = self
+            .execute(|mut client, timeout| {
+                let request = with_timeout(request.clone(), timeout);
+                async move { client.get_spread_candles(request).await }
+            })
+            .await?;
+
+        Ok(response
+            .candles
+            .into_iter()
+            .map(|candle| candle.into())
+            .collect())
+    }
+
+    /// Latest candles of every candle type and the last bid/ask, empty list requests all instruments
+    pub async fn get_last_candles(
+        &self,
+        instruments: &[String],
+    ) -> Result<Vec<InstrumentLastCandles>, CandlesClientError> {
+        let request = GetLastCandlesRequest {
+            instruments: instruments.to_vec(),
+        };
+
+        let response = self
+            .execute(|mut client, timeout| {
+                let request = with_timeout(request.clone(), timeout);
+                async move { client.get_last_candles(request).await }
+            })
+            .await?;
+
+        Ok(response
+            .instruments
+            .into_iter()
+            .map(|last_candles| last_candles.into())
+            .collect())
+    }
+
+    pub async fn get_last_candle(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+    ) -> Result<Option<CandleModel>, CandlesClientError> {
+        let last_candles = self.get_last_candles(&[instrument.to_string()]).await?;
+
+        Ok(last_candles
+            .into_iter()
+            .find(|last_candles| last_candles.instrument == instrument)
+            .and_then(|last_candles| last_candles.get(candle_type, side).cloned()))
+    }
+
+    pub async fn list_instruments(&self) -> Result<Vec<InstrumentInfo>, CandlesClientError> {
+        let response = self
+            .execute(|mut client, timeout| {
+                let request = with_timeout(ListInstrumentsRequest {}, timeout);
+                async move { client.list_instruments(request).await }
+            })
+            .await?;
+
+        Ok(response
+            .instruments
+            .into_iter()
+            .map(|instrument| instrument.into())
+            .collect())
+    }
+
+    async fn execute<T, F, Fut>(&self, call: F) -> Result<T, CandlesClientError>
+    where
+        F: Fn(CandlesServiceClient<Channel>, Duration) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, Status>>,
+    {
+        let mut attempt = 0;
+        let mut backoff = self.settings.initial_backoff;
+
+        loop {
+            let result = match self.get_client().await {
+                Ok(client) => call(client, self.settings.timeout)
+                    .await
+                    .map(|response| response.into_inner())
+                    .map_err(CandlesClientError::Status),
+                Err(err) => Err(CandlesClientError::Connect(err)),
+            };
+
+            match result {
+                Err(err) if err.is_retryable() && attempt < self.settings.max_retries => {
+                    attempt += 1;
+                    self.client.lock().await.take();
+                    tokio::time::sleep(backoff).await;
+                    backoff = Duration::min(backoff * 2, self.settings.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn get_client(&self) -> Result<CandlesServiceClient<Channel>, tonic::transport::Error> {
+        let mut client = self.client.lock().await;
+
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let channel = Endpoint::from_shared(self.settings.url.clone())?
+            .connect_timeout(self.settings.connect_timeout)
+            .connect()
+            .await?;
+
+        let connected = CandlesServiceClient::new(channel);
+        *client = Some(connected.clone());
+
+        Ok(connected)
+    }
+}
+
+fn with_timeout<T>(message: T, timeout: Duration) -> Request<T> {
+    let mut request = Request::new(message);
+    request.set_timeout(timeout);
+    request
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use service_candle_writer_generated_proto::{
+    CandleGrpcModel, CandleTypeGrpc, GetCandlesResponse, InstrumentGrpcModel,
+    InstrumentLastCandlesGrpc, LastCandleGrpc, LastPriceGrpc, PriceSideGrpc, SpreadCandleGrpcModel,
+};
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum CandleType {
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Minute5,
+    Minute15,
+    Minute30,
+    Hour4,
+    Week,
+}
+
+impl From<CandleType> for CandleTypeGrpc {
+    fn from(candle_type: CandleType) -> Self {
+        match candle_type {
+            CandleType::Minute => CandleTypeGrpc::Minute,
+            CandleType::Hour => CandleTypeGrpc::Hour,
+            CandleType::Day => CandleTypeGrpc::Day,
+            CandleType::Month => CandleTypeGrpc::Month,
+            CandleType::Minute5 => CandleTypeGrpc::Minute5,
+            CandleType::Minute15 => CandleTypeGrpc::Minute15,
+            CandleType::Minute30 => CandleTypeGrpc::Minute30,
+            CandleType::Hour4 => CandleTypeGrpc::Hour4,
+            CandleType::Week => CandleTypeGrpc::Week,
+        }
+    }
+}
+
+impl From<CandleTypeGrpc> for CandleType {
+    fn from(candle_type: CandleTypeGrpc) -> Self {
+        match candle_type {
+            CandleTypeGrpc::Minute => CandleType::Minute,
+            CandleTypeGrpc::Hour => CandleType::Hour,
+            CandleTypeGrpc::Day => CandleType::Day,
+            CandleTypeGrpc::Month => CandleType::Month,
+            CandleTypeGrpc::Minute5 => CandleType::Minute5,
+            CandleTypeGrpc::Minute15 => CandleType::Minute15,
+            CandleTypeGrpc::Minute30 => CandleType::Minute30,
+            CandleTypeGrpc::Hour4 => CandleType::Hour4,
+            CandleTypeGrpc::Week => CandleType::Week,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum PriceSide {
+    Bid,
+    Ask,
+    Mid,
+    Trade,
+}
+
+impl From<PriceSide> for PriceSideGrpc {
+    fn from(side: PriceSide) -> Self {
+        match side {
+            PriceSide::Bid => PriceSideGrpc::Bid,
+            PriceSide::Ask => PriceSideGrpc::Ask,
+            PriceSide::Mid => PriceSideGrpc::Mid,
+            PriceSide::Trade => PriceSideGrpc::Trade,
+        }
+    }
+}
+
+impl From<PriceSideGrpc> for PriceSide {
+    fn from(side: PriceSideGrpc) -> Self {
+        match side {
+            PriceSideGrpc::Bid => PriceSide::Bid,
+            PriceSideGrpc::Ask => PriceSide::Ask,
+            PriceSideGrpc::Mid => PriceSide::Mid,
+            PriceSideGrpc::Trade => PriceSide::Trade,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleModel {
+    pub open: f64,
+    pub close: f64,
+    pub high: f64,
+    pub low: f64,
+    pub datetime: u64,
+    pub ticks: u64,
+    /// None when none of the ticks of the candle carried a volume
+    pub volume: Option<f64>,
+    pub vwap: f64,
+    /// Flat candle of a period without ticks
+    pub synthetic: bool,
+}
+
+impl From<CandleGrpcModel> for CandleModel {
+    fn from(candle: CandleGrpcModel) -> Self {
+        CandleModel {
+            open: candle.open,
+            close: candle.close,
+            high: candle.high,
+            low: candle.low,
+            datetime: candle.datetime,
+            ticks: candle.ticks,
+            volume: candle.has_volume.then_some(candle.volume),
+            vwap: candle.vwap,
+            synthetic: candle.synthetic,
+        }
+    }
+}
+
+/// Candles of a history request with the price precision of the instrument
+#[derive(Debug, Clone)]
+pub struct CandlesHistory {
+    pub candles: Vec<CandleModel>,
+    /// Empty when the precision of the instrument is not configured
+    pub digits: Option<u32>,
+}
+
+impl From<GetCandlesResponse> for CandlesHistory {
+    fn from(response: GetCandlesResponse) -> Self {
+        CandlesHistory {
+            candles: response
+                .candles
+                .into_iter()
+                .map(|candle| candle.into())
+                .collect(),
+            digits: from_grpc_digits(response.digits),
+        }
+    }
+}
+
+// negative digits stand for an unknown precision
+fn from_grpc_digits(digits: i32) -> Option<u32> {
+    u32::try_from(digits).ok()
+}
+
+/// Spread (ask - bid) statistics of a candle period
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadCandleModel {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub close: f64,
+    pub datetime: u64,
+}
+
+impl From<SpreadCandleGrpcModel> for SpreadCandleModel {
+    fn from(candle: SpreadCandleGrpcModel) -> Self {
+        SpreadCandleModel {
+            min: candle.min,
+            max: candle.max,
+            avg: candle.avg,
+            close: candle.close,
+            datetime: candle.datetime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastPrice {
+    pub bid: f64,
+    pub ask: f64,
+    pub datetime: u64,
+}
+
+impl From<LastPriceGrpc> for LastPrice {
+    fn from(price: LastPriceGrpc) -> Self {
+        LastPrice {
+            bid: price.bid,
+            ask: price.ask,
+            datetime: price.unix_time_sec,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstrumentLastCandles {
+    pub instrument: String,
+    pub bid: HashMap<CandleType, CandleModel>,
+    pub ask: HashMap<CandleType, CandleModel>,
+    pub mid: HashMap<CandleType, CandleModel>,
+    pub trade: HashMap<CandleType, CandleModel>,
+    /// Empty until the first tick of the instrument since the service start
+    pub last_price: Option<LastPrice>,
+}
+
+impl InstrumentLastCandles {
+    pub fn get(&self, candle_type: CandleType, side: PriceSide) -> Option<&CandleModel> {
+        match side {
+            PriceSide::Bid => self.bid.get(&candle_type),
+            PriceSide::Ask => self.ask.get(&candle_type),
+            PriceSide::Mid => self.mid.get(&candle_type),
+            PriceSide::Trade => self.trade.get(&candle_type),
+        }
+    }
+}
+
+fn to_last_candles_map(candles: Vec<LastCandleGrpc>) -> HashMap<CandleType, CandleModel> {
+    let mut result = HashMap::with_capacity(candles.len());
+
+    for last_candle in candles {
+        let candle_type = CandleTypeGrpc::from_i32(last_candle.candle_type);
+
+        if let (Some(candle_type), Some(candle)) = (candle_type, last_candle.candle) {
+            result.insert(candle_type.into(), candle.into());
+        }
+    }
+
+    result
+}
+
+impl From<InstrumentLastCandlesGrpc> for InstrumentLastCandles {
+    fn from(last_candles: InstrumentLastCandlesGrpc) -> Self {
+        InstrumentLastCandles {
+            instrument: last_candles.instrument,
+            bid: to_last_candles_map(last_candles.bid),
+            ask: to_last_candles_map(last_candles.ask),
+            mid: to_last_candles_map(last_candles.mid),
+            trade: to_last_candles_map(last_candles.trade),
+            last_price: last_candles.last_price.map(|price| price.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstrumentInfo {
+    pub instrument: String,
+    /// 0 for instruments registered before it was tracked
+    pub first_seen: u64,
+    pub last_tick: u64,
+    pub tick_count: u64,
+    pub candle_types: Vec<CandleType>,
+    /// Empty when the precision of the instrument is not configured
+    pub digits: Option<u32>,
+    /// Empty for instruments missing in the instrument dictionary
+    pub base_asset: String,
+    pub quote_asset: String,
+}
+
+impl From<InstrumentGrpcModel> for InstrumentInfo {
+    fn from(instrument: InstrumentGrpcModel) -> Self {
+        InstrumentInfo {
+            instrument: instrument.instrument,
+            first_seen: instrument.first_seen,
+            last_tick: instrument.last_tick,
+            tick_count: instrument.tick_count,
+            candle_types: instrument
+                .candle_types
+                .into_iter()
+                .filter_map(CandleTypeGrpc::from_i32)
+                .map(|candle_type| candle_type.into())
+                .collect(),
+            digits: from_grpc_digits(instrument.digits),
+            base_asset: instrument.base_asset,
+            quote_asset: instrument.quote_asset,
+        }
+    }
+}
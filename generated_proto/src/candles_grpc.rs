@@ -0,0 +1,779 @@
+/// Dates are unix timestamps in seconds
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetCandlesRequest {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(enumeration = "CandleTypeGrpc", tag = "2")]
+    pub candle_type: i32,
+    #[prost(enumeration = "PriceSideGrpc", tag = "3")]
+    pub side: i32,
+    #[prost(uint64, tag = "4")]
+    pub from: u64,
+    #[prost(uint64, tag = "5")]
+    pub to: u64,
+    /// Emit flat synthetic candles for the periods without ticks
+    #[prost(bool, tag = "6")]
+    pub fill_gaps: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CandleGrpcModel {
+    #[prost(uint64, tag = "1")]
+    pub datetime: u64,
+    #[prost(double, tag = "2")]
+    pub open: f64,
+    #[prost(double, tag = "3")]
+    pub close: f64,
+    #[prost(double, tag = "4")]
+    pub high: f64,
+    #[prost(double, tag = "5")]
+    pub low: f64,
+    #[prost(uint64, tag = "6")]
+    pub ticks: u64,
+    #[prost(double, tag = "7")]
+    pub volume: f64,
+    #[prost(double, tag = "8")]
+    pub vwap: f64,
+    /// Flat candle of a period without ticks
+    #[prost(bool, tag = "9")]
+    pub synthetic: bool,
+    /// false when none of the ticks of the candle carried a volume, volume is 0 then
+    #[prost(bool, tag = "10")]
+    pub has_volume: bool,
+}
+/// digits is -1 when the price precision of the instrument is not configured
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetCandlesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub candles: ::prost::alloc::vec::Vec<CandleGrpcModel>,
+    #[prost(int32, tag = "2")]
+    pub digits: i32,
+}
+/// Empty lists subscribe to all instruments and all candle types
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeCandlesRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub instruments: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(enumeration = "CandleTypeGrpc", repeated, tag = "2")]
+    pub candle_types: ::prost::alloc::vec::Vec<i32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CandleUpdateGrpc {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(enumeration = "CandleTypeGrpc", tag = "2")]
+    pub candle_type: i32,
+    #[prost(enumeration = "PriceSideGrpc", tag = "3")]
+    pub side: i32,
+    #[prost(message, optional, tag = "4")]
+    pub candle: ::core::option::Option<CandleGrpcModel>,
+}
+/// Empty list requests all instruments
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLastCandlesRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub instruments: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LastCandleGrpc {
+    #[prost(enumeration = "CandleTypeGrpc", tag = "1")]
+    pub candle_type: i32,
+    #[prost(message, optional, tag = "2")]
+    pub candle: ::core::option::Option<CandleGrpcModel>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LastPriceGrpc {
+    #[prost(double, tag = "1")]
+    pub bid: f64,
+    #[prost(double, tag = "2")]
+    pub ask: f64,
+    #[prost(uint64, tag = "3")]
+    pub unix_time_sec: u64,
+}
+/// last_price is empty until the first tick of the instrument since the service start
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InstrumentLastCandlesGrpc {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub bid: ::prost::alloc::vec::Vec<LastCandleGrpc>,
+    #[prost(message, repeated, tag = "3")]
+    pub ask: ::prost::alloc::vec::Vec<LastCandleGrpc>,
+    #[prost(message, optional, tag = "4")]
+    pub last_price: ::core::option::Option<LastPriceGrpc>,
+    #[prost(message, repeated, tag = "5")]
+    pub mid: ::prost::alloc::vec::Vec<LastCandleGrpc>,
+    #[prost(message, repeated, tag = "6")]
+    pub trade: ::prost::alloc::vec::Vec<LastCandleGrpc>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLastCandlesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub instruments: ::prost::alloc::vec::Vec<InstrumentLastCandlesGrpc>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListInstrumentsRequest {}
+/// first_seen is 0 for instruments registered before it was tracked,
+/// digits is -1 when the price precision of the instrument is not configured,
+/// the assets are empty for instruments missing in the instrument dictionary
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InstrumentGrpcModel {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub first_seen: u64,
+    #[prost(uint64, tag = "3")]
+    pub last_tick: u64,
+    #[prost(uint64, tag = "4")]
+    pub tick_count: u64,
+    #[prost(enumeration = "CandleTypeGrpc", repeated, tag = "5")]
+    pub candle_types: ::prost::alloc::vec::Vec<i32>,
+    #[prost(int32, tag = "6")]
+    pub digits: i32,
+    #[prost(string, tag = "7")]
+    pub base_asset: ::prost::alloc::string::String,
+    #[prost(string, tag = "8")]
+    pub quote_asset: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListInstrumentsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub instruments: ::prost::alloc::vec::Vec<InstrumentGrpcModel>,
+}
+/// Dates are unix timestamps in seconds
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSpreadCandlesRequest {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(enumeration = "CandleTypeGrpc", tag = "2")]
+    pub candle_type: i32,
+    #[prost(uint64, tag = "3")]
+    pub from: u64,
+    #[prost(uint64, tag = "4")]
+    pub to: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SpreadCandleGrpcModel {
+    #[prost(uint64, tag = "1")]
+    pub datetime: u64,
+    #[prost(double, tag = "2")]
+    pub min: f64,
+    #[prost(double, tag = "3")]
+    pub max: f64,
+    #[prost(double, tag = "4")]
+    pub avg: f64,
+    #[prost(double, tag = "5")]
+    pub close: f64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSpreadCandlesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub candles: ::prost::alloc::vec::Vec<SpreadCandleGrpcModel>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum CandleTypeGrpc {
+    Minute = 0,
+    Hour = 1,
+    Day = 2,
+    Month = 3,
+    Minute5 = 4,
+    Minute15 = 5,
+    Minute30 = 6,
+    Hour4 = 7,
+    Week = 8,
+}
+impl CandleTypeGrpc {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            CandleTypeGrpc::Minute => "Minute",
+            CandleTypeGrpc::Hour => "Hour",
+            CandleTypeGrpc::Day => "Day",
+            CandleTypeGrpc::Month => "Month",
+            CandleTypeGrpc::Minute5 => "Minute5",
+            CandleTypeGrpc::Minute15 => "Minute15",
+            CandleTypeGrpc::Minute30 => "Minute30",
+            CandleTypeGrpc::Hour4 => "Hour4",
+            CandleTypeGrpc::Week => "Week",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Minute" => Some(Self::Minute),
+            "Hour" => Some(Self::Hour),
+            "Day" => Some(Self::Day),
+            "Month" => Some(Self::Month),
+            "Minute5" => Some(Self::Minute5),
+            "Minute15" => Some(Self::Minute15),
+            "Minute30" => Some(Self::Minute30),
+            "Hour4" => Some(Self::Hour4),
+            "Week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PriceSideGrpc {
+    Bid = 0,
+    Ask = 1,
+    Mid = 2,
+    Trade = 3,
+}
+impl PriceSideGrpc {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            PriceSideGrpc::Bid => "Bid",
+            PriceSideGrpc::Ask => "Ask",
+            PriceSideGrpc::Mid => "Mid",
+            PriceSideGrpc::Trade => "Trade",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Bid" => Some(Self::Bid),
+            "Ask" => Some(Self::Ask),
+            "Mid" => Some(Self::Mid),
+            "Trade" => Some(Self::Trade),
+            _ => None,
+        }
+    }
+}
+/// Generated client implementations.
+pub mod candles_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Candles read API of the candle writer.
+    #[derive(Debug, Clone)]
+    pub struct CandlesServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl CandlesServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> CandlesServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> CandlesServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            CandlesServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Candles of the instrument for the [from, to) range
+        pub async fn get_candles(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetCandlesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/candles_grpc.CandlesService/GetCandles",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Current candles of the instruments followed by every update of them
+        pub async fn subscribe_candles(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeCandlesRequest>,
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::CandleUpdateGrpc>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/candles_grpc.CandlesService/SubscribeCandles",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// Latest candle of every candle type for every side and the last bid/ask of the instruments
+        pub async fn get_last_candles(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetLastCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetLastCandlesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/candles_grpc.CandlesService/GetLastCandles",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Known instruments with their metadata
+        pub async fn list_instruments(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListInstrumentsRequest>,
+        ) -> Result<tonic::Response<super::ListInstrumentsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/candles_grpc.CandlesService/ListInstruments",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Spread (ask - bid) statistics of the instrument for the [from, to) range
+        pub async fn get_spread_candles(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSpreadCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetSpreadCandlesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/candles_grpc.CandlesService/GetSpreadCandles",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod candles_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with CandlesServiceServer.
+    #[async_trait]
+    pub trait CandlesService: Send + Sync + 'static {
+        /// Candles of the instrument for the [from, to) range
+        async fn get_candles(
+            &self,
+            request: tonic::Request<super::GetCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetCandlesResponse>, tonic::Status>;
+        /// Server streaming response type for the SubscribeCandles method.
+        type SubscribeCandlesStream: futures_core::Stream<
+                Item = Result<super::CandleUpdateGrpc, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Current candles of the instruments followed by every update of them
+        async fn subscribe_candles(
+            &self,
+            request: tonic::Request<super::SubscribeCandlesRequest>,
+        ) -> Result<tonic::Response<Self::SubscribeCandlesStream>, tonic::Status>;
+        /// Latest candle of every candle type for every side and the last bid/ask of the instruments
+        async fn get_last_candles(
+            &self,
+            request: tonic::Request<super::GetLastCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetLastCandlesResponse>, tonic::Status>;
+        /// Known instruments with their metadata
+        async fn list_instruments(
+            &self,
+            request: tonic::Request<super::ListInstrumentsRequest>,
+        ) -> Result<tonic::Response<super::ListInstrumentsResponse>, tonic::Status>;
+        /// Spread (ask - bid) statistics of the instrument for the [from, to) range
+        async fn get_spread_candles(
+            &self,
+            request: tonic::Request<super::GetSpreadCandlesRequest>,
+        ) -> Result<tonic::Response<super::GetSpreadCandlesResponse>, tonic::Status>;
+    }
+    /// Candles read API of the candle writer.
+    #[derive(Debug)]
+    pub struct CandlesServiceServer<T: CandlesService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: CandlesService> CandlesServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for CandlesServiceServer<T>
+    where
+        T: CandlesService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/candles_grpc.CandlesService/GetCandles" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetCandlesSvc<T: CandlesService>(pub Arc<T>);
+                    impl<
+                        T: CandlesService,
+                    > tonic::server::UnaryService<super::GetCandlesRequest>
+                    for GetCandlesSvc<T> {
+                        type Response = super::GetCandlesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetCandlesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_candles(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetCandlesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/candles_grpc.CandlesService/SubscribeCandles" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeCandlesSvc<T: CandlesService>(pub Arc<T>);
+                    impl<
+                        T: CandlesService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeCandlesRequest,
+                    > for SubscribeCandlesSvc<T> {
+                        type Response = super::CandleUpdateGrpc;
+                        type ResponseStream = T::SubscribeCandlesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeCandlesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).subscribe_candles(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribeCandlesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/candles_grpc.CandlesService/GetLastCandles" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetLastCandlesSvc<T: CandlesService>(pub Arc<T>);
+                    impl<
+                        T: CandlesService,
+                    > tonic::server::UnaryService<super::GetLastCandlesRequest>
+                    for GetLastCandlesSvc<T> {
+                        type Response = super::GetLastCandlesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetLastCandlesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_last_candles(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetLastCandlesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/candles_grpc.CandlesService/ListInstruments" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListInstrumentsSvc<T: CandlesService>(pub Arc<T>);
+                    impl<
+                        T: CandlesService,
+                    > tonic::server::UnaryService<super::ListInstrumentsRequest>
+                    for ListInstrumentsSvc<T> {
+                        type Response = super::ListInstrumentsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListInstrumentsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_instruments(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListInstrumentsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/candles_grpc.CandlesService/GetSpreadCandles" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSpreadCandlesSvc<T: CandlesService>(pub Arc<T>);
+                    impl<
+                        T: CandlesService,
+                    > tonic::server::UnaryService<super::GetSpreadCandlesRequest>
+                    for GetSpreadCandlesSvc<T> {
+                        type Response = super::GetSpreadCandlesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetSpreadCandlesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_spread_candles(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetSpreadCandlesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: CandlesService> Clone for CandlesServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
+        }
+    }
+    impl<T: CandlesService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: CandlesService> tonic::server::NamedService for CandlesServiceServer<T> {
+        const NAME: &'static str = "candles_grpc.CandlesService";
+    }
+}
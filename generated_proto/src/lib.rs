@@ -1,9 +1,13 @@
-pub mod rust_grpc_service;
+pub mod candles_grpc;
 pub mod service_candle_writer_messages;
 pub mod bid_ask_traits;
 pub mod candle_message_traits;
+pub mod trade_traits;
+pub mod rejected_bid_ask_traits;
 
-pub use rust_grpc_service::*;
+pub use candles_grpc::*;
 pub use service_candle_writer_messages::*;
 pub use bid_ask_traits::*;
 pub use candle_message_traits::*;
+pub use trade_traits::*;
+pub use rejected_bid_ask_traits::*;
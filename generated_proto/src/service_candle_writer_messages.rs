@@ -10,6 +10,34 @@ pub struct BidAsk {
     pub ask: f64,
     #[prost(uint64, tag = "5")]
     pub unix_time_sec: u64,
+    /// Set only when has_volume is, feeds without volume leave both empty
+    #[prost(double, tag = "6")]
+    pub volume: f64,
+    #[prost(bool, tag = "7")]
+    pub has_volume: bool,
+}
+/// Quote rejected by the tick validation, published to the quarantine topic
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectedBidAsk {
+    #[prost(message, optional, tag = "1")]
+    pub bid_ask: ::core::option::Option<BidAsk>,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Trade {
+    #[prost(string, tag = "1")]
+    pub instrument: ::prost::alloc::string::String,
+    #[prost(double, tag = "2")]
+    pub price: f64,
+    #[prost(double, tag = "3")]
+    pub volume: f64,
+    #[prost(enumeration = "TradeSide", tag = "4")]
+    pub side: i32,
+    #[prost(uint64, tag = "5")]
+    pub unix_time_sec: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -22,6 +50,10 @@ pub struct CandleMessage {
     pub bid: ::core::option::Option<CandleGroup>,
     #[prost(message, optional, tag = "4")]
     pub ask: ::core::option::Option<CandleGroup>,
+    #[prost(message, optional, tag = "5")]
+    pub mid: ::core::option::Option<CandleGroup>,
+    #[prost(message, optional, tag = "6")]
+    pub trade: ::core::option::Option<CandleGroup>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -34,6 +66,16 @@ pub struct CandleGroup {
     pub day: ::core::option::Option<CandleItem>,
     #[prost(message, optional, tag = "4")]
     pub month: ::core::option::Option<CandleItem>,
+    #[prost(message, optional, tag = "5")]
+    pub minute5: ::core::option::Option<CandleItem>,
+    #[prost(message, optional, tag = "6")]
+    pub minute15: ::core::option::Option<CandleItem>,
+    #[prost(message, optional, tag = "7")]
+    pub minute30: ::core::option::Option<CandleItem>,
+    #[prost(message, optional, tag = "8")]
+    pub hour4: ::core::option::Option<CandleItem>,
+    #[prost(message, optional, tag = "9")]
+    pub week: ::core::option::Option<CandleItem>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -46,4 +88,39 @@ pub struct CandleItem {
     pub low: f64,
     #[prost(double, tag = "10")]
     pub close: f64,
+    #[prost(uint64, tag = "12")]
+    pub ticks: u64,
+    #[prost(double, tag = "14")]
+    pub volume: f64,
+    #[prost(double, tag = "16")]
+    pub vwap: f64,
+    /// false when none of the ticks of the candle carried a volume
+    #[prost(bool, tag = "18")]
+    pub has_volume: bool,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum TradeSide {
+    Buy = 0,
+    Sell = 1,
+}
+impl TradeSide {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            TradeSide::Buy => "Buy",
+            TradeSide::Sell => "Sell",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Buy" => Some(Self::Buy),
+            "Sell" => Some(Self::Sell),
+            _ => None,
+        }
+    }
 }
@@ -1,27 +1,45 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use crate::{
-    caches::CandlesInstrumentsCache,
-    domain::{Database, DatabaseImpl, InstrumentStorage, RequestCounter, CandlesPersistentAzureStorage},
+    caches::{CandlesInstrumentsCache, SpreadsCache, TickMetrics},
+    domain::{
+        AzureInstrumentMetadataStorage, CandlesPersistentAzureStorage, CandlesStorage,
+        CandlesStorageType, FileCandlesStorage, InMemoryCandlesStorage, InstrumentDictionary,
+        InstrumentMetadataStorage, InstrumentStorage, PriceSanityValidator, SpikeValidator,
+        SpreadPersistentAzureStorage, SpreadsStorage, SqliteCandlesStorage, TickValidation,
+        TickValidator,
+    },
+    models::{CandleUpdate, DayRollovers, SessionCalendars},
+    no_sql::spot_instrument::SpotInstrumentNoSqlEntity,
     settings_model::SettingsModel,
-    subscribers::BidAskSubscriber,
+    subscribers::{BidAskSubscriber, TradeSubscriber},
 };
 use azure_data_tables::prelude::TableServiceClient;
 use azure_storage::StorageCredentials;
-use my_no_sql_tcp_reader::MyNoSqlTcpConnectionSettings;
+use my_no_sql_tcp_reader::{MyNoSqlTcpConnection, MyNoSqlTcpConnectionSettings};
 use my_service_bus_tcp_client::{MyServiceBusClient, MyServiceBusSettings};
+use tokio::sync::{broadcast, Mutex};
+
+// live updates buffered for every gRPC subscription before it is considered lagged
+const CANDLE_UPDATES_CAPACITY: usize = 100_000;
 
 pub struct AppContext {
     pub states: rust_service_sdk::app::global_states::GlobalStates,
-    pub database: Arc<dyn Database<RequestCounter> + Sync + Send>,
     pub service_bus: Arc<MyServiceBusClient>,
-    pub table_service_ask: Arc<TableServiceClient>,
-    pub table_service_bid: Arc<TableServiceClient>,
     pub cache: Arc<CandlesInstrumentsCache>,
+    pub tick_metrics: Arc<TickMetrics>,
+    pub day_rollovers: Arc<DayRollovers>,
+    pub session_calendars: Arc<SessionCalendars>,
     pub instrument_storage: Arc<InstrumentStorage>,
+    pub instrument_dictionary: Arc<InstrumentDictionary>,
     pub settings: SettingsModel,
-    pub candles_persistent_azure_storage: Arc<CandlesPersistentAzureStorage>
-    //_my_no_sql_tcp_connection: my_no_sql_tcp_reader::MyNoSqlTcpConnection,
+    pub candles_storage: Arc<dyn CandlesStorage>,
+    pub spreads_cache: Arc<SpreadsCache>,
+    pub spreads_storage: Arc<dyn SpreadsStorage>,
+    pub candle_updates: broadcast::Sender<CandleUpdate>,
+    /// Serializes the persistence cycle with the admin operations rewriting the stored history
+    pub history_lock: Mutex<()>,
+    _my_no_sql_tcp_connection: MyNoSqlTcpConnection,
 }
 
 struct RealMyServiceBusSettings {
@@ -51,7 +69,7 @@ impl AppContext {
         let service_bus_settings = RealMyServiceBusSettings {
             host_port: settings.inner.spot_service_bus_hos_port.clone(),
         };
-        let _no_sql_settings = RealMyNoSqlTcpConnectionSettings {
+        let no_sql_settings = RealMyNoSqlTcpConnectionSettings {
             host_port: settings.inner.my_no_sql_reader_host_port.clone(),
         };
 
@@ -60,43 +78,72 @@ impl AppContext {
             "service-candle-writer",
             &settings.inner.spot_service_bus_hos_port,
             Arc::new(service_bus_settings),
-            logger,
+            logger.clone(),
         ));
 
+        let my_no_sql_tcp_connection = MyNoSqlTcpConnection::new(
+            "service-candle-writer".to_string(),
+            Arc::new(no_sql_settings),
+        );
+        let spot_instruments_reader = my_no_sql_tcp_connection
+            .get_reader::<SpotInstrumentNoSqlEntity>()
+            .await;
+        my_no_sql_tcp_connection.start(logger).await;
+
+        let tick_metrics = Arc::new(TickMetrics::new());
+        let day_rollovers = Arc::new(DayRollovers::new(&settings.inner.day_rollovers));
+        let session_calendars = Arc::new(SessionCalendars::new(&settings.inner.session_calendars));
+
         let cache = Arc::new(CandlesInstrumentsCache::new(
-            settings.inner.minute_limit,
-            settings.inner.hour_limit,
+            settings.inner.get_timeframes(),
+            settings.inner.tick_lateness_sec,
+            tick_metrics.clone(),
+            day_rollovers.clone(),
         ));
 
-        let storage_credentials = StorageCredentials::Key(
-            settings.inner.azure_storage_account_ask.clone(),
-            settings.inner.azure_storage_access_key_ask.clone(),
-        );
-        let table_service = TableServiceClient::new(
-            settings.inner.azure_storage_account_ask.clone(),
-            storage_credentials,
-        );
-        let table_client = table_service;
-        let table_service_ask = Arc::new(table_client);
+        let spreads_cache = Arc::new(SpreadsCache::new(
+            settings.inner.get_timeframes(),
+            settings.inner.tick_lateness_sec,
+            day_rollovers.clone(),
+        ));
 
-        let storage_credentials = StorageCredentials::Key(
-            settings.inner.azure_storage_account_bid.clone(),
-            settings.inner.azure_storage_access_key_bid.clone(),
-        );
-        let table_service = TableServiceClient::new(
-            settings.inner.azure_storage_account_bid.clone(),
-            storage_credentials,
-        );
-        let table_client = table_service;
-        let table_service_bid = Arc::new(table_client);
+        let (candles_storage, spreads_storage, instrument_metadata_storage) =
+            create_storages(&settings, day_rollovers.clone());
+
+        let instrument_storage = Arc::new(InstrumentStorage::new(
+            instrument_metadata_storage,
+            settings.inner.instrument_digits.clone(),
+        ));
+        let instrument_dictionary = Arc::new(InstrumentDictionary::new(
+            spot_instruments_reader,
+            instrument_storage.clone(),
+            settings.inner.instrument_aliases.clone(),
+        ));
 
+        let (candle_updates, _) = broadcast::channel(CANDLE_UPDATES_CAPACITY);
 
-        let instrument_storage = Arc::new(InstrumentStorage::new(table_service_ask.clone()));
+        let mut validators: Vec<Box<dyn TickValidator>> = vec![Box::new(PriceSanityValidator)];
+        if let Some(max_deviation_percent) = settings.inner.max_tick_deviation_percent {
+            validators.push(Box::new(SpikeValidator::new(
+                max_deviation_percent,
+                settings.inner.spike_recent_price_sec,
+            )));
+        }
+        let tick_validation = Arc::new(TickValidation::new(
+            validators,
+            settings.inner.quarantine_rejected_ticks,
+        ));
 
         let subscriber = BidAskSubscriber::new(
             cache.clone(),
             service_bus.clone(),
             instrument_storage.clone(),
+            candle_updates.clone(),
+            spreads_cache.clone(),
+            tick_validation,
+            tick_metrics.clone(),
+            session_calendars.clone(),
+            instrument_dictionary.clone(),
         );
 
         service_bus
@@ -107,25 +154,124 @@ impl AppContext {
             )
             .await;
 
-        let candle_persistence_azure_storage = Arc::new(
-            crate::domain::CandlesPersistentAzureStorage::new(
-                table_service_ask.clone(),
-                table_service_bid.clone()));
+        let trade_subscriber = TradeSubscriber::new(
+            cache.clone(),
+            service_bus.clone(),
+            instrument_storage.clone(),
+            candle_updates.clone(),
+            instrument_dictionary.clone(),
+        );
+
+        service_bus
+            .subscribe(
+                "service-candle-writer".to_string(),
+                my_service_bus_abstractions::subscriber::TopicQueueType::Permanent,
+                Arc::new(trade_subscriber),
+            )
+            .await;
 
         Self {
             states: rust_service_sdk::app::global_states::GlobalStates::new(),
-            database: Arc::new(DatabaseImpl::new()),
             service_bus,
-            table_service_ask,
-            table_service_bid,
             cache,
+            tick_metrics,
+            day_rollovers,
+            session_calendars,
             instrument_storage,
+            instrument_dictionary,
             settings: settings,
-            candles_persistent_azure_storage: candle_persistence_azure_storage
+            candles_storage,
+            spreads_cache,
+            spreads_storage,
+            candle_updates,
+            history_lock: Mutex::new(()),
+            _my_no_sql_tcp_connection: my_no_sql_tcp_connection,
         }
     }
 }
 
+/// Storages of the candles, the spread candles and the instrument metadata of the selected backend
+fn create_storages(
+    settings: &SettingsModel,
+    day_rollovers: Arc<DayRollovers>,
+) -> (
+    Arc<dyn CandlesStorage>,
+    Arc<dyn SpreadsStorage>,
+    Arc<dyn InstrumentMetadataStorage>,
+) {
+    match settings.inner.candles_storage {
+        CandlesStorageType::Azure => {
+            let table_service_ask = create_table_service(
+                &settings.inner.azure_storage_account_ask,
+                &settings.inner.azure_storage_access_key_ask,
+            )
+            .expect("AzureStorageAccountAsk and AzureStorageAccessKeyAsk are required by the Azure candles storage");
+            let table_service_bid = create_table_service(
+                &settings.inner.azure_storage_account_bid,
+                &settings.inner.azure_storage_access_key_bid,
+            )
+            .expect("AzureStorageAccountBid and AzureStorageAccessKeyBid are required by the Azure candles storage");
+            let table_service_mid = create_table_service(
+                &settings.inner.azure_storage_account_mid,
+                &settings.inner.azure_storage_access_key_mid,
+            );
+            let table_service_trade = create_table_service(
+                &settings.inner.azure_storage_account_trade,
+                &settings.inner.azure_storage_access_key_trade,
+            );
+
+            (
+                Arc::new(CandlesPersistentAzureStorage::new(
+                    table_service_ask.clone(),
+                    table_service_bid,
+                    table_service_mid,
+                    table_service_trade,
+                    day_rollovers.clone(),
+                )),
+                Arc::new(SpreadPersistentAzureStorage::new(
+                    table_service_ask.clone(),
+                    day_rollovers,
+                )),
+                Arc::new(AzureInstrumentMetadataStorage::new(table_service_ask)),
+            )
+        }
+        CandlesStorageType::InMemory => {
+            let storage = Arc::new(InMemoryCandlesStorage::new());
+            (storage.clone(), storage.clone(), storage)
+        }
+        CandlesStorageType::File => {
+            let storage = Arc::new(FileCandlesStorage::new(
+                settings.inner.candles_storage_path.clone(),
+                day_rollovers,
+            ));
+            (storage.clone(), storage.clone(), storage)
+        }
+        CandlesStorageType::Sqlite => {
+            let storage = Arc::new(
+                SqliteCandlesStorage::new(Path::new(&settings.inner.candles_storage_path))
+                    .unwrap_or_else(|err| panic!("Can't open the SQLite candles storage: {}", err)),
+            );
+            (storage.clone(), storage.clone(), storage)
+        }
+    }
+}
+
+fn create_table_service(
+    account: &Option<String>,
+    access_key: &Option<String>,
+) -> Option<Arc<TableServiceClient>> {
+    match (account, access_key) {
+        (Some(account), Some(access_key)) => {
+            let storage_credentials = StorageCredentials::Key(account.clone(), access_key.clone());
+            Some(Arc::new(TableServiceClient::new(
+                account.clone(),
+                storage_credentials,
+            )))
+        }
+        _ => None,
+    }
+}
+
 impl rust_service_sdk::app::app_ctx::GetGlobalState for AppContext {
     fn is_initialized(&self) -> bool {
         self.states.is_initialized()
@@ -147,11 +293,19 @@ impl rust_service_sdk::app::app_ctx::InitGrpc for AppContext {
         &self,
         server: Box<std::cell::RefCell<tonic::transport::Server>>,
     ) -> tonic::transport::server::Router {
-        let bookstore = crate::services::BookStoreImpl::new(self.database.clone());
+        let candles_service = crate::services::CandlesServiceImpl::new(
+            self.cache.clone(),
+            self.candles_storage.clone(),
+            self.instrument_storage.clone(),
+            self.candle_updates.clone(),
+            self.spreads_cache.clone(),
+            self.spreads_storage.clone(),
+            self.session_calendars.clone(),
+        );
 
         server.borrow_mut().add_service(
-            service_candle_writer_generated_proto::bookstore_server::BookstoreServer::new(
-                bookstore,
+            service_candle_writer_generated_proto::candles_service_server::CandlesServiceServer::new(
+                candles_service,
             ),
         )
     }
@@ -1,4 +1,4 @@
-use crate::models::{CandleModel, CandleType};
+use crate::models::{CandleModel, CandleType, DayRollover};
 use std::collections::BTreeMap;
 
 #[derive(Debug, Clone)]
@@ -12,22 +12,25 @@ pub struct CandlesCache {
     pub candle_type: CandleType,
     pub candles: BTreeMap<u64, CandleModel>,
     cache_type: CacheType,
+    rollover: DayRollover,
 }
 
 impl CandlesCache {
-    pub fn new(candle_type: CandleType) -> Self {
+    pub fn new(candle_type: CandleType, rollover: DayRollover) -> Self {
         Self {
             candle_type: candle_type,
             candles: BTreeMap::new(),
             cache_type: CacheType::UnLimited,
+            rollover,
         }
     }
 
-    pub fn with_capacity(candle_type: CandleType, capacity: usize) -> Self {
+    pub fn with_capacity(candle_type: CandleType, capacity: usize, rollover: DayRollover) -> Self {
         Self {
             candle_type: candle_type,
             candles: BTreeMap::new(),
             cache_type: CacheType::Limited(capacity),
+            rollover,
         }
     }
 
@@ -35,30 +38,42 @@ impl CandlesCache {
         self.candles.insert(candle.datetime, candle);
     }
 
-    pub fn handle_new_rate(&mut self, date: u64, rate: f64) -> (CandleType, CandleModel) {
-        let date = self.candle_type.format_date_by_type(date);
+    /// Returns None for a late tick of a candle that was already evicted from the cache
+    pub fn handle_new_rate(
+        &mut self,
+        date: u64,
+        rate: f64,
+        volume: Option<f64>,
+    ) -> Option<(CandleType, CandleModel)> {
+        let candle_date = self.rollover.format_date(self.candle_type, date);
 
-        let target_candle = self.candles.get_mut(&date);
+        let target_candle = self.candles.get_mut(&candle_date);
 
         match target_candle {
             Some(candle) => {
-                candle.update_by_rate(rate);
-                (self.candle_type, candle.clone())
+                candle.update_by_rate(rate, volume, date);
+                Some((self.candle_type, candle.clone()))
             }
             None => {
-                let candle_model = CandleModel::new_from_rate(self.candle_type.clone(), date, rate);
-
                 // Cache resizing
                 if let CacheType::Limited(capacity) = self.cache_type {
                     if self.candles.len() >= capacity {
+                        if self
+                            .get_first_date()
+                            .map_or(false, |first| candle_date < first)
+                        {
+                            return None;
+                        }
+
                         let key_to_remove = *self.candles.keys().next().unwrap();
                         self.candles.remove(&key_to_remove);
                     }
                 }
 
+                let candle_model = CandleModel::new_from_rate(candle_date, date, rate, volume);
                 let response = candle_model.clone();
-                self.candles.insert(date, candle_model);
-                (self.candle_type, response)
+                self.candles.insert(candle_date, candle_model);
+                Some((self.candle_type, response))
             }
         }
     }
@@ -73,6 +88,36 @@ impl CandlesCache {
         result
     }
 
+    pub fn get_last(&self) -> Option<CandleModel> {
+        self.candles.values().next_back().cloned()
+    }
+
+    pub fn get_last_before(&self, date: u64) -> Option<CandleModel> {
+        self.candles
+            .range(..date)
+            .next_back()
+            .map(|(_, candle)| candle.clone())
+    }
+
+    pub fn get_first_date(&self) -> Option<u64> {
+        self.candles.keys().next().copied()
+    }
+
+    /// Merges a candle of another name of the instrument, candles older than the cache are skipped
+    pub fn merge(&mut self, candle: &CandleModel) {
+        match self.get_first_date() {
+            Some(first) if candle.datetime >= first => {}
+            _ => return,
+        }
+
+        match self.candles.get_mut(&candle.datetime) {
+            Some(cached) => *cached = cached.merge(candle),
+            None => {
+                self.candles.insert(candle.datetime, candle.clone());
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.candles.clear()
     }
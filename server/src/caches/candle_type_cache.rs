@@ -1,34 +1,68 @@
-use crate::models::{CandleModel, CandleType};
+use crate::models::{CandleModel, CandleType, DayRollover, Timeframe};
 
-use super::CandlesCache;
+use super::{CacheType, CandlesCache};
+
+/// Updated candles of every active candle type
+pub type CandleTypeUpdates = Vec<(CandleType, CandleModel)>;
+
+impl From<&Timeframe> for CacheType {
+    fn from(timeframe: &Timeframe) -> Self {
+        match timeframe.limit {
+            Some(limit) => CacheType::Limited(limit),
+            None => CacheType::UnLimited,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct CandleTypeCache {
     pub instrument_id: String,
-    pub candles_by_minute: CandlesCache,
-    pub candles_by_hour: CandlesCache,
-    pub candles_by_day: CandlesCache,
-    pub candles_by_month: CandlesCache,
+    pub caches: Vec<CandlesCache>,
+    /// Timestamp of the latest aggregated tick
+    pub last_tick_date: u64,
+    tick_lateness_sec: u64,
 }
 
 impl CandleTypeCache {
-    pub fn new(instrument_id: String, minute_capacity: usize, hour_capacity: usize) -> Self {
+    pub fn new(
+        instrument_id: String,
+        timeframes: &[Timeframe],
+        tick_lateness_sec: u64,
+        rollover: DayRollover,
+    ) -> Self {
         Self {
-            instrument_id: instrument_id,
-            candles_by_minute: CandlesCache::with_capacity(CandleType::Minute, minute_capacity),
-            candles_by_hour: CandlesCache::with_capacity(CandleType::Hour, hour_capacity),
-            candles_by_day: CandlesCache::new(CandleType::Day),
-            candles_by_month: CandlesCache::new(CandleType::Month),
+            instrument_id,
+            last_tick_date: 0,
+            tick_lateness_sec,
+            caches: timeframes
+                .iter()
+                .map(|timeframe| match CacheType::from(timeframe) {
+                    CacheType::Limited(capacity) => {
+                        CandlesCache::with_capacity(timeframe.candle_type, capacity, rollover)
+                    }
+                    CacheType::UnLimited => CandlesCache::new(timeframe.candle_type, rollover),
+                })
+                .collect(),
         }
     }
 
+    fn get_cache(&self, candle_type: CandleType) -> Option<&CandlesCache> {
+        self.caches
+            .iter()
+            .find(|cache| cache.candle_type == candle_type)
+    }
+
     pub fn init(&mut self, candle: CandleModel, candle_type: CandleType) {
-        match candle_type {
-            CandleType::Minute => self.candles_by_minute.init(candle),
-            CandleType::Hour => self.candles_by_hour.init(candle),
-            CandleType::Day => self.candles_by_day.init(candle),
-            CandleType::Month => self.candles_by_month.init(candle),
-        };
+        self.last_tick_date = self.last_tick_date.max(candle.close_time);
+
+        let cache = self
+            .caches
+            .iter_mut()
+            .find(|cache| cache.candle_type == candle_type);
+
+        if let Some(cache) = cache {
+            cache.init(candle);
+        }
     }
 
     pub fn get_by_date_range(
@@ -37,30 +71,63 @@ impl CandleTypeCache {
         date_from: u64,
         date_to: u64,
     ) -> Vec<CandleModel> {
-        match candle_type {
-            CandleType::Minute => self.candles_by_minute.get_by_date_range(date_from, date_to),
-            CandleType::Hour => self.candles_by_hour.get_by_date_range(date_from, date_to),
-            CandleType::Day => self.candles_by_day.get_by_date_range(date_from, date_to),
-            CandleType::Month => self.candles_by_month.get_by_date_range(date_from, date_to),
+        self.get_cache(candle_type)
+            .map(|cache| cache.get_by_date_range(date_from, date_to))
+            .unwrap_or_default()
+    }
+
+    pub fn get_last(&self, candle_type: CandleType) -> Option<CandleModel> {
+        self.get_cache(candle_type)
+            .and_then(|cache| cache.get_last())
+    }
+
+    pub fn get_last_before(&self, candle_type: CandleType, date: u64) -> Option<CandleModel> {
+        self.get_cache(candle_type)
+            .and_then(|cache| cache.get_last_before(date))
+    }
+
+    pub fn get_first_date(&self, candle_type: CandleType) -> Option<u64> {
+        self.get_cache(candle_type)
+            .and_then(|cache| cache.get_first_date())
+    }
+
+    pub fn merge(&mut self, candle_type: CandleType, candles: &[CandleModel]) {
+        let cache = self
+            .caches
+            .iter_mut()
+            .find(|cache| cache.candle_type == candle_type);
+
+        if let Some(cache) = cache {
+            for candle in candles {
+                cache.merge(candle);
+            }
         }
     }
 
-    pub fn handle_new_rate(&mut self, rate: f64, date: u64) -> ((CandleType, CandleModel), 
-                                                                (CandleType, CandleModel), 
-                                                                (CandleType, CandleModel), 
-                                                                (CandleType, CandleModel)) {
-        (
-            self.candles_by_minute.handle_new_rate(date, rate),
-            self.candles_by_hour.handle_new_rate(date, rate),
-            self.candles_by_day.handle_new_rate(date, rate),
-            self.candles_by_month.handle_new_rate(date, rate),
+    /// Returns None for a tick older than the lateness window, such ticks are dropped
+    pub fn handle_new_rate(
+        &mut self,
+        rate: f64,
+        volume: Option<f64>,
+        date: u64,
+    ) -> Option<CandleTypeUpdates> {
+        if date + self.tick_lateness_sec < self.last_tick_date {
+            return None;
+        }
+
+        self.last_tick_date = self.last_tick_date.max(date);
+
+        Some(
+            self.caches
+                .iter_mut()
+                .filter_map(|cache| cache.handle_new_rate(date, rate, volume))
+                .collect(),
         )
     }
 
     pub fn clear(&mut self) {
-        self.candles_by_day.clear();
-        self.candles_by_hour.clear();
-        self.candles_by_minute.clear();
-        self.candles_by_month.clear();
+        for cache in self.caches.iter_mut() {
+            cache.clear();
+        }
     }
 }
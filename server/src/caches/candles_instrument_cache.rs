@@ -1,156 +1,222 @@
-use crate::models::{CandleModel, CandleType, CandlesBidAsk};
-use std::collections::HashMap;
+use crate::models::{
+    CandleModel, CandleType, CandlesBidAsk, CandlesTrade, DayRollover, DayRollovers, PriceSide,
+    Timeframe,
+};
+use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 
-use super::CandleTypeCache;
+use super::{CandleTypeCache, CandleTypeUpdates, TickMetrics};
 
 pub struct CandlesInstrumentsCache {
     pub bid_candles: RwLock<HashMap<String, CandleTypeCache>>,
     pub ask_candles: RwLock<HashMap<String, CandleTypeCache>>,
-    minute_capacity: usize,
-    hour_capacity: usize,
+    pub mid_candles: RwLock<HashMap<String, CandleTypeCache>>,
+    pub trade_candles: RwLock<HashMap<String, CandleTypeCache>>,
+    pub last_prices: RwLock<HashMap<String, CandlesBidAsk>>,
+    timeframes: Vec<Timeframe>,
+    tick_lateness_sec: u64,
+    tick_metrics: Arc<TickMetrics>,
+    day_rollovers: Arc<DayRollovers>,
 }
 
 impl CandlesInstrumentsCache {
-    pub fn new(minute_capacity: usize, hour_capacity: usize) -> Self {
+    pub fn new(
+        timeframes: Vec<Timeframe>,
+        tick_lateness_sec: u64,
+        tick_metrics: Arc<TickMetrics>,
+        day_rollovers: Arc<DayRollovers>,
+    ) -> Self {
         Self {
             bid_candles: RwLock::new(HashMap::new()),
             ask_candles: RwLock::new(HashMap::new()),
-            minute_capacity,
-            hour_capacity,
+            mid_candles: RwLock::new(HashMap::new()),
+            trade_candles: RwLock::new(HashMap::new()),
+            last_prices: RwLock::new(HashMap::new()),
+            timeframes,
+            tick_lateness_sec,
+            tick_metrics,
+            day_rollovers,
+        }
+    }
+
+    pub fn get_day_rollover(&self, instrument: &str) -> DayRollover {
+        self.day_rollovers.get(instrument)
+    }
+
+    pub fn get_candles(&self, side: PriceSide) -> &RwLock<HashMap<String, CandleTypeCache>> {
+        match side {
+            PriceSide::Bid => &self.bid_candles,
+            PriceSide::Ask => &self.ask_candles,
+            PriceSide::Mid => &self.mid_candles,
+            PriceSide::Trade => &self.trade_candles,
         }
     }
 
     pub async fn update(
         &self,
         prices: Vec<CandlesBidAsk>,
-    ) -> (
-        Vec<(CandleType, CandleModel)>,
-        Vec<(CandleType, CandleModel)>,
-    ) {
-        (
-            self.update_bid_or_ask(true, &prices).await,
-            self.update_bid_or_ask(false, &prices).await,
-        )
+    ) -> Vec<(PriceSide, Vec<(CandleType, CandleModel)>)> {
+        for price in prices.iter() {
+            self.update_last_price(price).await;
+        }
+
+        let mut result = Vec::with_capacity(PriceSide::QUOTES.len());
+        for side in PriceSide::QUOTES {
+            result.push((side, self.update_side(side, &prices).await));
+        }
+
+        result
     }
 
-    pub async fn update_once(
-        &self,
-        price: CandlesBidAsk,
-    ) -> (
-        (
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-        ),
-        (
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-            (CandleType, CandleModel),
-        ),
-    ) {
-        (
-            self.update_bid_or_ask_once(true, &price).await,
-            self.update_bid_or_ask_once(false, &price).await,
-        )
+    pub async fn update_once(&self, price: CandlesBidAsk) -> Vec<(PriceSide, CandleTypeUpdates)> {
+        self.update_last_price(&price).await;
+
+        let mut result = Vec::with_capacity(PriceSide::QUOTES.len());
+        for side in PriceSide::QUOTES {
+            result.push((side, self.update_side_once(side, &price).await));
+        }
+
+        result
     }
 
-    async fn update_bid_or_ask(
+    pub async fn update_trade(&self, trade: &CandlesTrade) -> CandleTypeUpdates {
+        let mut write_lock = self.get_candles(PriceSide::Trade).write().await;
+
+        let candle_updates = self.handle_new_rate(
+            &mut write_lock,
+            &trade.instrument,
+            trade.price,
+            Some(trade.volume),
+            trade.date,
+        );
+
+        self.handle_late_tick(candle_updates, &trade.instrument, PriceSide::Trade)
+            .await
+    }
+
+    async fn update_last_price(&self, price: &CandlesBidAsk) {
+        let mut last_prices = self.last_prices.write().await;
+
+        match last_prices.get_mut(&price.instrument) {
+            Some(last_price) => {
+                if last_price.date <= price.date {
+                    *last_price = price.clone();
+                }
+            }
+            None => {
+                last_prices.insert(price.instrument.clone(), price.clone());
+            }
+        }
+    }
+
+    pub fn get_timeframes(&self) -> &[Timeframe] {
+        &self.timeframes
+    }
+
+    pub async fn get_last_price(&self, instument_id: &str) -> Option<CandlesBidAsk> {
+        self.last_prices.read().await.get(instument_id).cloned()
+    }
+
+    async fn update_side(
         &self,
-        is_bid: bool,
-        prices: &Vec<CandlesBidAsk>,
+        side: PriceSide,
+        prices: &[CandlesBidAsk],
     ) -> Vec<(CandleType, CandleModel)> {
-        let mut write_lock = match is_bid {
-            true => self.bid_candles.write().await,
-            false => self.ask_candles.write().await,
-        };
+        let mut write_lock = self.get_candles(side).write().await;
 
-        let mut result = Vec::with_capacity(prices.len() * 4);
+        let mut result = Vec::with_capacity(prices.len() * self.timeframes.len());
         for bid_ask in prices.iter() {
-            let target_instruments_cache = write_lock.get_mut(&bid_ask.instrument);
-            let target_rate = match is_bid {
-                true => bid_ask.bid,
-                false => bid_ask.ask,
-            };
+            if let Some(rate) = side.get_rate(bid_ask) {
+                let candle_updates = self.handle_new_rate(
+                    &mut write_lock,
+                    &bid_ask.instrument,
+                    rate,
+                    bid_ask.volume,
+                    bid_ask.date,
+                );
 
-            let candle_updates;
-            match target_instruments_cache {
-                Some(cache) => {
-                    candle_updates = cache.handle_new_rate(target_rate, bid_ask.date);
-                }
-                None => {
-                    let mut cache = CandleTypeCache::new(
-                        bid_ask.instrument.clone(),
-                        self.minute_capacity,
-                        self.hour_capacity,
-                    );
-                    candle_updates = cache.handle_new_rate(target_rate, bid_ask.date);
-                    write_lock.insert(bid_ask.instrument.clone(), cache);
-                }
+                result.extend(
+                    self.handle_late_tick(candle_updates, &bid_ask.instrument, side)
+                        .await,
+                );
             }
-
-            result.push(candle_updates.0);
-            result.push(candle_updates.1);
-            result.push(candle_updates.2);
-            result.push(candle_updates.3);
         }
 
         result
     }
 
-    async fn update_bid_or_ask_once(
+    async fn update_side_once(
         &self,
-        is_bid: bool,
+        side: PriceSide,
         bid_ask: &CandlesBidAsk,
-    ) -> (
-        (CandleType, CandleModel),
-        (CandleType, CandleModel),
-        (CandleType, CandleModel),
-        (CandleType, CandleModel),
-    ) {
-        let mut write_lock = match is_bid {
-            true => self.bid_candles.write().await,
-            false => self.ask_candles.write().await,
-        };
+    ) -> CandleTypeUpdates {
+        let mut write_lock = self.get_candles(side).write().await;
+
+        match side.get_rate(bid_ask) {
+            Some(rate) => {
+                let candle_updates = self.handle_new_rate(
+                    &mut write_lock,
+                    &bid_ask.instrument,
+                    rate,
+                    bid_ask.volume,
+                    bid_ask.date,
+                );
 
-        let target_instruments_cache = write_lock.get_mut(&bid_ask.instrument);
-        let target_rate = match is_bid {
-            true => bid_ask.bid,
-            false => bid_ask.ask,
-        };
+                self.handle_late_tick(candle_updates, &bid_ask.instrument, side)
+                    .await
+            }
+            None => vec![],
+        }
+    }
 
-        let candle_updates;
-        match target_instruments_cache {
-            Some(cache) => {
-                candle_updates = cache.handle_new_rate(target_rate, bid_ask.date);
+    async fn handle_late_tick(
+        &self,
+        candle_updates: Option<CandleTypeUpdates>,
+        instrument: &str,
+        side: PriceSide,
+    ) -> CandleTypeUpdates {
+        match candle_updates {
+            Some(candle_updates) => candle_updates,
+            None => {
+                tracing::warn!("Dropped late {:?} tick of {}", side, instrument);
+                self.tick_metrics.record_late_tick(instrument, side).await;
+                vec![]
             }
+        }
+    }
+
+    fn handle_new_rate(
+        &self,
+        candles: &mut HashMap<String, CandleTypeCache>,
+        instrument: &str,
+        rate: f64,
+        volume: Option<f64>,
+        date: u64,
+    ) -> Option<CandleTypeUpdates> {
+        match candles.get_mut(instrument) {
+            Some(cache) => cache.handle_new_rate(rate, volume, date),
             None => {
                 let mut cache = CandleTypeCache::new(
-                    bid_ask.instrument.clone(),
-                    self.minute_capacity,
-                    self.hour_capacity,
+                    instrument.to_string(),
+                    &self.timeframes,
+                    self.tick_lateness_sec,
+                    self.day_rollovers.get(instrument),
                 );
-                candle_updates = cache.handle_new_rate(target_rate, bid_ask.date);
-                write_lock.insert(bid_ask.instrument.clone(), cache);
+                let candle_updates = cache.handle_new_rate(rate, volume, date);
+                candles.insert(instrument.to_string(), cache);
+                candle_updates
             }
         }
-
-        candle_updates
     }
 
     pub async fn init(
         &self,
         instument_id: String,
-        is_bid: bool,
+        side: PriceSide,
         candle_type: CandleType,
         candle: CandleModel,
     ) {
-        let mut target_cache = match is_bid {
-            true => self.bid_candles.write().await,
-            false => self.ask_candles.write().await,
-        };
+        let mut target_cache = self.get_candles(side).write().await;
 
         let instrument_cache = target_cache.get_mut(&instument_id);
 
@@ -161,8 +227,9 @@ impl CandlesInstrumentsCache {
             None => {
                 let mut cache = CandleTypeCache::new(
                     instument_id.clone(),
-                    self.minute_capacity,
-                    self.hour_capacity,
+                    &self.timeframes,
+                    self.tick_lateness_sec,
+                    self.day_rollovers.get(&instument_id),
                 );
                 cache.init(candle, candle_type);
                 target_cache.insert(instument_id, cache);
@@ -174,14 +241,11 @@ impl CandlesInstrumentsCache {
         &self,
         instument_id: String,
         candle_type: CandleType,
-        is_bid: bool,
+        side: PriceSide,
         start_date: u64,
         end_date: u64,
     ) -> Vec<CandleModel> {
-        let target_cache = match is_bid {
-            true => self.bid_candles.read().await,
-            false => self.ask_candles.read().await,
-        };
+        let target_cache = self.get_candles(side).read().await;
 
         let instrument_cache = target_cache.get(&instument_id);
 
@@ -193,27 +257,111 @@ impl CandlesInstrumentsCache {
         }
     }
 
+    /// Merges candles of another name of the instrument into its cached candles
+    pub async fn merge(
+        &self,
+        instument_id: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+        candles: &[CandleModel],
+    ) {
+        let mut target_cache = self.get_candles(side).write().await;
+
+        if let Some(cache) = target_cache.get_mut(instument_id) {
+            cache.merge(candle_type, candles);
+        }
+    }
+
+    pub async fn get_last_candle(
+        &self,
+        instument_id: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+    ) -> Option<CandleModel> {
+        let target_cache = self.get_candles(side).read().await;
+
+        target_cache
+            .get(instument_id)
+            .and_then(|cache| cache.get_last(candle_type))
+    }
+
+    pub async fn get_last_before(
+        &self,
+        instument_id: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+        date: u64,
+    ) -> Option<CandleModel> {
+        let target_cache = self.get_candles(side).read().await;
+
+        target_cache
+            .get(instument_id)
+            .and_then(|cache| cache.get_last_before(candle_type, date))
+    }
+
+    pub async fn get_first_date(
+        &self,
+        instument_id: &str,
+        candle_type: CandleType,
+        side: PriceSide,
+    ) -> Option<u64> {
+        let target_cache = self.get_candles(side).read().await;
+
+        target_cache
+            .get(instument_id)
+            .and_then(|cache| cache.get_first_date(candle_type))
+    }
+
+    pub async fn remove(&self, instument_id: &str) {
+        for side in PriceSide::ALL {
+            self.get_candles(side).write().await.remove(instument_id);
+        }
+        self.last_prices.write().await.remove(instument_id);
+    }
+
     pub async fn clear(&mut self) {
-        {
-            let mut bids = self.bid_candles.write().await;
-            bids.clear();
+        for side in PriceSide::ALL {
+            let mut candles = self.get_candles(side).write().await;
+            candles.clear();
         }
         {
-            let mut asks = self.ask_candles.write().await;
-            asks.clear();
+            let mut last_prices = self.last_prices.write().await;
+            last_prices.clear();
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::CandlesBidAsk;
+    use std::sync::Arc;
+
+    use crate::models::{
+        CandleType, CandlesBidAsk, CandlesTrade, DayRollovers, PriceSide, Timeframe,
+    };
+
+    use super::{CandlesInstrumentsCache, TickMetrics};
+
+    fn create_cache(timeframes: Vec<Timeframe>) -> CandlesInstrumentsCache {
+        CandlesInstrumentsCache::new(
+            timeframes,
+            300,
+            Arc::new(TickMetrics::new()),
+            Arc::new(DayRollovers::default()),
+        )
+    }
 
-    use super::CandlesInstrumentsCache;
+    fn get_timeframes(limit: usize) -> Vec<Timeframe> {
+        vec![
+            Timeframe::new(CandleType::Minute, Some(limit)),
+            Timeframe::new(CandleType::Hour, Some(limit)),
+            Timeframe::new(CandleType::Day, None),
+            Timeframe::new(CandleType::Month, None),
+        ]
+    }
 
     #[tokio::test]
     async fn test_sinle_quote() {
-        let cache = CandlesInstrumentsCache::new(100, 100);
+        let cache = create_cache(get_timeframes(100));
         let instument = String::from("EURUSD");
 
         let bid_ask = CandlesBidAsk {
@@ -221,6 +369,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 25.55,
             ask: 36.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -229,7 +378,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -238,7 +387,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -248,7 +397,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Hour,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -257,7 +406,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Hour,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -267,7 +416,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Day,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -276,7 +425,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Day,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -286,7 +435,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Month,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -295,7 +444,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Month,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -316,7 +465,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_date_rotation_minute() {
-        let cache = CandlesInstrumentsCache::new(100, 100);
+        let cache = create_cache(get_timeframes(100));
         let instument = String::from("EURUSD");
 
         let bid_ask = CandlesBidAsk {
@@ -324,6 +473,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 25.55,
             ask: 36.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -333,6 +483,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 25.55,
             ask: 36.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -341,7 +492,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -350,7 +501,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -360,7 +511,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Hour,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -369,7 +520,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Hour,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -379,7 +530,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Day,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -388,7 +539,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Day,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -398,7 +549,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Month,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -407,7 +558,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Month,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -428,7 +579,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_calculation() {
-        let cache = CandlesInstrumentsCache::new(100, 100);
+        let cache = create_cache(get_timeframes(100));
         let instument = String::from("EURUSD");
 
         let bid_ask = CandlesBidAsk {
@@ -436,6 +587,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 25.55,
             ask: 36.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -445,6 +597,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 60.55,
             ask: 31.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -454,6 +607,7 @@ mod tests {
             instrument: instument.clone(),
             bid: 50.55,
             ask: 62.55,
+            volume: None,
         };
 
         cache.update(vec![bid_ask]).await;
@@ -462,7 +616,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -471,7 +625,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -500,7 +654,7 @@ mod tests {
     #[tokio::test]
     async fn test_minute_limit() {
         let limit = 100;
-        let cache = CandlesInstrumentsCache::new(limit, limit);
+        let cache = create_cache(get_timeframes(limit));
         let instument = String::from("EURUSD");
 
         let mut arr = Vec::with_capacity(limit);
@@ -511,6 +665,7 @@ mod tests {
                 instrument: instument.clone(),
                 bid: 25.55 + i as f64,
                 ask: 35.55 + i as f64,
+                volume: None,
             };
 
             arr.push(bid_ask);
@@ -522,7 +677,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                true,
+                PriceSide::Bid,
                 1660559404,
                 2660559404,
             )
@@ -531,7 +686,7 @@ mod tests {
             .get_by_date_range(
                 instument.clone(),
                 crate::models::CandleType::Minute,
-                false,
+                PriceSide::Ask,
                 1660559404,
                 2660559404,
             )
@@ -558,4 +713,185 @@ mod tests {
         assert_eq!(last_ask.high, 35.55 + add);
         assert_eq!(last_ask.low, 35.55 + add);
     }
+
+    #[tokio::test]
+    async fn test_last_candle() {
+        let cache = create_cache(get_timeframes(100));
+        let instument = String::from("EURUSD");
+
+        let bid_ask = CandlesBidAsk {
+            date: 1662559404,
+            instrument: instument.clone(),
+            bid: 25.55,
+            ask: 36.55,
+            volume: Some(1.5),
+        };
+
+        cache.update(vec![bid_ask]).await;
+
+        let bid_ask = CandlesBidAsk {
+            date: 1662559474,
+            instrument: instument.clone(),
+            bid: 26.55,
+            ask: 37.55,
+            volume: Some(2.0),
+        };
+
+        cache.update(vec![bid_ask]).await;
+
+        let last_bid_minute = cache
+            .get_last_candle(
+                &instument,
+                crate::models::CandleType::Minute,
+                PriceSide::Bid,
+            )
+            .await
+            .unwrap();
+        let last_ask_hour = cache
+            .get_last_candle(&instument, crate::models::CandleType::Hour, PriceSide::Ask)
+            .await
+            .unwrap();
+
+        assert_eq!(last_bid_minute.datetime, 1662559440);
+        assert_eq!(last_bid_minute.open, 26.55);
+
+        assert_eq!(last_ask_hour.datetime, 1662559200);
+        assert_eq!(last_ask_hour.open, 36.55);
+        assert_eq!(last_ask_hour.close, 37.55);
+        assert_eq!(last_ask_hour.ticks, 2);
+        assert_eq!(last_ask_hour.volume, Some(3.5));
+        assert_eq!(last_bid_minute.ticks, 1);
+
+        assert!(cache
+            .get_last_candle("GBPUSD", crate::models::CandleType::Minute, PriceSide::Bid)
+            .await
+            .is_none());
+
+        let last_price = cache.get_last_price(&instument).await.unwrap();
+
+        assert_eq!(last_price.bid, 26.55);
+        assert_eq!(last_price.ask, 37.55);
+        assert_eq!(last_price.date, 1662559474);
+    }
+
+    #[tokio::test]
+    async fn test_inactive_timeframe() {
+        let cache = create_cache(vec![Timeframe::new(CandleType::Hour4, None)]);
+
+        let updates = cache
+            .update_once(CandlesBidAsk {
+                date: 1662559404,
+                instrument: String::from("EURUSD"),
+                bid: 25.55,
+                ask: 36.55,
+                volume: None,
+            })
+            .await;
+
+        for (_, candles) in updates {
+            assert_eq!(candles.len(), 1);
+            assert_eq!(candles[0].0, CandleType::Hour4);
+            assert_eq!(candles[0].1.datetime, 1662552000);
+        }
+
+        assert!(cache
+            .get_last_candle("EURUSD", CandleType::Minute, PriceSide::Bid)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trade_vwap() {
+        let cache = create_cache(get_timeframes(100));
+
+        for (date, price, volume) in [
+            (1662559404, 10.0, 1.0),
+            (1662559410, 13.0, 2.0),
+            (1662559420, 9.0, 0.0),
+        ] {
+            cache
+                .update_trade(&CandlesTrade {
+                    date,
+                    instrument: String::from("EURUSD"),
+                    price,
+                    volume,
+                })
+                .await;
+        }
+
+        let candle = cache
+            .get_last_candle("EURUSD", CandleType::Minute, PriceSide::Trade)
+            .await
+            .unwrap();
+
+        assert_eq!(candle.ticks, 3);
+        assert_eq!(candle.volume, Some(3.0));
+        assert_eq!(candle.vwap, 12.0);
+        assert_eq!(candle.close, 9.0);
+
+        assert!(cache
+            .get_last_candle("EURUSD", CandleType::Minute, PriceSide::Bid)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_ticks() {
+        let tick_metrics = Arc::new(TickMetrics::new());
+        let cache = CandlesInstrumentsCache::new(
+            get_timeframes(100),
+            300,
+            tick_metrics.clone(),
+            Arc::new(DayRollovers::default()),
+        );
+
+        // the third tick is older than the first one, the fourth is out of the lateness window
+        for (date, price) in [
+            (1662559404, 10.0),
+            (1662559430, 12.0),
+            (1662559400, 9.0),
+            (1662559000, 7.0),
+        ] {
+            cache
+                .update_trade(&CandlesTrade {
+                    date,
+                    instrument: String::from("EURUSD"),
+                    price,
+                    volume: 1.0,
+                })
+                .await;
+        }
+
+        let candle = cache
+            .get_last_candle("EURUSD", CandleType::Minute, PriceSide::Trade)
+            .await
+            .unwrap();
+
+        assert_eq!(candle.open, 9.0);
+        assert_eq!(candle.close, 12.0);
+        assert_eq!(candle.low, 9.0);
+        assert_eq!(candle.ticks, 3);
+        assert_eq!(candle.open_time, 1662559400);
+        assert_eq!(candle.close_time, 1662559430);
+
+        assert_eq!(
+            tick_metrics
+                .get_late_ticks("EURUSD", PriceSide::Trade)
+                .await,
+            1
+        );
+        assert_eq!(
+            cache
+                .get_by_date_range(
+                    String::from("EURUSD"),
+                    CandleType::Minute,
+                    PriceSide::Trade,
+                    0,
+                    u64::MAX
+                )
+                .await
+                .len(),
+            1
+        );
+    }
 }
@@ -1,7 +1,11 @@
 mod candle_cache;
 mod candle_type_cache;
 mod candles_instrument_cache;
+mod spread_cache;
+mod tick_metrics;
 
 pub use candle_cache::*;
 pub use candle_type_cache::*;
-pub use candles_instrument_cache::*;
\ No newline at end of file
+pub use candles_instrument_cache::*;
+pub use spread_cache::*;
+pub use tick_metrics::*;
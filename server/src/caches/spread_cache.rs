@@ -0,0 +1,320 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use tokio::sync::RwLock;
+
+use crate::models::{
+    CandleType, CandlesBidAsk, DayRollover, DayRollovers, SpreadCandleModel, Timeframe,
+};
+
+use super::CacheType;
+
+#[derive(Debug, Clone)]
+pub struct SpreadCandlesCache {
+    pub candle_type: CandleType,
+    pub candles: BTreeMap<u64, SpreadCandleModel>,
+    /// Timestamp of the latest aggregated tick
+    pub last_tick_date: u64,
+    cache_type: CacheType,
+    rollover: DayRollover,
+    tick_lateness_sec: u64,
+}
+
+impl SpreadCandlesCache {
+    pub fn new(
+        candle_type: CandleType,
+        cache_type: CacheType,
+        rollover: DayRollover,
+        tick_lateness_sec: u64,
+    ) -> Self {
+        Self {
+            candle_type,
+            candles: BTreeMap::new(),
+            last_tick_date: 0,
+            cache_type,
+            rollover,
+            tick_lateness_sec,
+        }
+    }
+
+    pub fn init(&mut self, candle: SpreadCandleModel) {
+        self.last_tick_date = self.last_tick_date.max(candle.close_time);
+        self.candles.insert(candle.datetime, candle);
+    }
+
+    /// Returns None for a tick older than the lateness window and for a late tick
+    /// of a candle that was already evicted from the cache, such ticks are dropped
+    pub fn handle_new_spread(&mut self, date: u64, spread: f64) -> Option<SpreadCandleModel> {
+        if date + self.tick_lateness_sec < self.last_tick_date {
+            return None;
+        }
+
+        self.last_tick_date = self.last_tick_date.max(date);
+        let candle_date = self.rollover.format_date(self.candle_type, date);
+
+        if let Some(candle) = self.candles.get_mut(&candle_date) {
+            candle.update_by_spread(spread, date);
+            return Some(candle.clone());
+        }
+
+        if let CacheType::Limited(capacity) = self.cache_type {
+            if self.candles.len() >= capacity {
+                if self
+                    .get_first_date()
+                    .map_or(false, |first| candle_date < first)
+                {
+                    return None;
+                }
+
+                let key_to_remove = *self.candles.keys().next().unwrap();
+                self.candles.remove(&key_to_remove);
+            }
+        }
+
+        let candle = SpreadCandleModel::new_from_spread(candle_date, date, spread);
+        self.candles.insert(candle_date, candle.clone());
+        Some(candle)
+    }
+
+    pub fn get_by_date_range(&self, date_from: u64, date_to: u64) -> Vec<SpreadCandleModel> {
+        self.candles
+            .range(date_from..date_to)
+            .map(|(_, candle)| candle.clone())
+            .collect()
+    }
+
+    pub fn get_first_date(&self) -> Option<u64> {
+        self.candles.keys().next().copied()
+    }
+
+    /// Merges a candle of another name of the instrument, candles older than the cache are skipped
+    pub fn merge(&mut self, candle: &SpreadCandleModel) {
+        match self.get_first_date() {
+            Some(first) if candle.datetime >= first => {}
+            _ => return,
+        }
+
+        match self.candles.get_mut(&candle.datetime) {
+            Some(cached) => *cached = cached.merge(candle),
+            None => {
+                self.candles.insert(candle.datetime, candle.clone());
+            }
+        }
+    }
+}
+
+/// Spread candles of every instrument and candle type
+pub struct SpreadsCache {
+    pub candles: RwLock<HashMap<String, HashMap<CandleType, SpreadCandlesCache>>>,
+    timeframes: Vec<Timeframe>,
+    tick_lateness_sec: u64,
+    day_rollovers: Arc<DayRollovers>,
+}
+
+impl SpreadsCache {
+    pub fn new(
+        timeframes: Vec<Timeframe>,
+        tick_lateness_sec: u64,
+        day_rollovers: Arc<DayRollovers>,
+    ) -> Self {
+        Self {
+            candles: RwLock::new(HashMap::new()),
+            timeframes,
+            tick_lateness_sec,
+            day_rollovers,
+        }
+    }
+
+    fn create_instrument_cache(&self, instrument: &str) -> HashMap<CandleType, SpreadCandlesCache> {
+        let rollover = self.day_rollovers.get(instrument);
+
+        self.timeframes
+            .iter()
+            .map(|timeframe| {
+                let cache = SpreadCandlesCache::new(
+                    timeframe.candle_type,
+                    timeframe.into(),
+                    rollover,
+                    self.tick_lateness_sec,
+                );
+                (timeframe.candle_type, cache)
+            })
+            .collect()
+    }
+
+    /// Ticks older than the lateness window of the instrument update none of the candles
+    pub async fn update(&self, bid_ask: &CandlesBidAsk) -> Vec<(CandleType, SpreadCandleModel)> {
+        let spread = bid_ask.ask - bid_ask.bid;
+        let mut write_lock = self.candles.write().await;
+
+        if !write_lock.contains_key(&bid_ask.instrument) {
+            let cache = self.create_instrument_cache(&bid_ask.instrument);
+            write_lock.insert(bid_ask.instrument.clone(), cache);
+        }
+
+        write_lock
+            .get_mut(&bid_ask.instrument)
+            .unwrap()
+            .iter_mut()
+            .filter_map(|(candle_type, cache)| {
+                let candle = cache.handle_new_spread(bid_ask.date, spread)?;
+                Some((*candle_type, candle))
+            })
+            .collect()
+    }
+
+    pub async fn init(&self, instrument: &str, candle_type: CandleType, candle: SpreadCandleModel) {
+        let mut write_lock = self.candles.write().await;
+
+        if !write_lock.contains_key(instrument) {
+            let cache = self.create_instrument_cache(instrument);
+            write_lock.insert(instrument.to_string(), cache);
+        }
+
+        if let Some(cache) = write_lock
+            .get_mut(instrument)
+            .unwrap()
+            .get_mut(&candle_type)
+        {
+            cache.init(candle);
+        }
+    }
+
+    pub async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel> {
+        let read_lock = self.candles.read().await;
+
+        read_lock
+            .get(instrument)
+            .and_then(|caches| caches.get(&candle_type))
+            .map(|cache| cache.get_by_date_range(date_from, date_to))
+            .unwrap_or_default()
+    }
+
+    /// Merges candles of another name of the instrument into its cached candles
+    pub async fn merge(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: &[SpreadCandleModel],
+    ) {
+        let mut write_lock = self.candles.write().await;
+
+        if let Some(cache) = write_lock
+            .get_mut(instrument)
+            .and_then(|caches| caches.get_mut(&candle_type))
+        {
+            for candle in candles {
+                cache.merge(candle);
+            }
+        }
+    }
+
+    pub async fn remove(&self, instrument: &str) {
+        self.candles.write().await.remove(instrument);
+    }
+
+    pub async fn get_first_date(&self, instrument: &str, candle_type: CandleType) -> Option<u64> {
+        let read_lock = self.candles.read().await;
+
+        read_lock
+            .get(instrument)
+            .and_then(|caches| caches.get(&candle_type))
+            .and_then(|cache| cache.get_first_date())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::models::{CandleType, CandlesBidAsk, DayRollovers, Timeframe};
+
+    use super::SpreadsCache;
+
+    #[tokio::test]
+    async fn test_spread_candles() {
+        let cache = SpreadsCache::new(
+            vec![
+                Timeframe::new(CandleType::Minute, Some(100)),
+                Timeframe::new(CandleType::Hour, Some(100)),
+            ],
+            300,
+            Arc::new(DayRollovers::default()),
+        );
+
+        for (date, bid, ask) in [
+            (1662559404, 1.0, 1.2),
+            (1662559410, 1.1, 1.2),
+            (1662559470, 1.0, 1.3),
+        ] {
+            cache
+                .update(&CandlesBidAsk {
+                    date,
+                    instrument: "EURUSD".to_string(),
+                    bid,
+                    ask,
+                    volume: None,
+                })
+                .await;
+        }
+
+        let minutes = cache
+            .get_by_date_range("EURUSD", CandleType::Minute, 0, u64::MAX)
+            .await;
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[0].ticks, 2);
+        assert!((minutes[0].min - 0.1).abs() < 1e-9);
+        assert!((minutes[0].max - 0.2).abs() < 1e-9);
+
+        let hours = cache
+            .get_by_date_range("EURUSD", CandleType::Hour, 0, u64::MAX)
+            .await;
+        assert_eq!(hours.len(), 1);
+        assert_eq!(hours[0].ticks, 3);
+        assert!((hours[0].close - 0.3).abs() < 1e-9);
+        assert!((hours[0].avg - 0.2).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_late_spreads() {
+        let cache = SpreadsCache::new(
+            vec![Timeframe::new(CandleType::Minute, Some(2))],
+            60,
+            Arc::new(DayRollovers::default()),
+        );
+
+        // the second tick is older than the first one, the fourth is out of the lateness window
+        for (date, ask, updated) in [
+            (1662559470, 1.2, true),
+            (1662559450, 1.1, true),
+            (1662559530, 1.3, true),
+            (1662559400, 1.4, false),
+        ] {
+            let spread = CandlesBidAsk {
+                date,
+                instrument: "EURUSD".to_string(),
+                bid: 1.0,
+                ask,
+                volume: None,
+            };
+
+            assert_eq!(!cache.update(&spread).await.is_empty(), updated);
+        }
+
+        let minutes = cache
+            .get_by_date_range("EURUSD", CandleType::Minute, 0, u64::MAX)
+            .await;
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[0].datetime, 1662559440);
+        assert_eq!(minutes[0].ticks, 2);
+        assert!((minutes[0].close - 0.2).abs() < 1e-9);
+    }
+}
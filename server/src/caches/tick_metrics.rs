@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use tokio::sync::RwLock;
+
+use crate::models::{PriceSide, TickRejection};
+
+// ticks of unknown instruments carry arbitrary names, so they are counted under a single label
+const UNKNOWN_INSTRUMENT_LABEL: &str = "unknown";
+
+/// Counters of the ticks the service did not aggregate
+pub struct TickMetrics {
+    late_ticks: RwLock<HashMap<(String, PriceSide), u64>>,
+    rejected_ticks: RwLock<HashMap<(String, TickRejection), u64>>,
+    out_of_session_ticks: RwLock<HashMap<String, u64>>,
+}
+
+impl TickMetrics {
+    pub fn new() -> Self {
+        Self {
+            late_ticks: RwLock::new(HashMap::new()),
+            rejected_ticks: RwLock::new(HashMap::new()),
+            out_of_session_ticks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn record_rejected_tick(&self, instrument: &str, reason: TickRejection) {
+        let instrument = match reason {
+            TickRejection::UnknownInstrument => UNKNOWN_INSTRUMENT_LABEL,
+            _ => instrument,
+        };
+
+        let mut write_lock = self.rejected_ticks.write().await;
+        *write_lock
+            .entry((instrument.to_string(), reason))
+            .or_insert(0) += 1;
+    }
+
+    pub async fn get_rejected_ticks(&self, instrument: &str) -> u64 {
+        let read_lock = self.rejected_ticks.read().await;
+        read_lock
+            .iter()
+            .filter(|((rejected_instrument, _), _)| rejected_instrument == instrument)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub async fn record_out_of_session_tick(&self, instrument: &str) {
+        let mut write_lock = self.out_of_session_ticks.write().await;
+        *write_lock.entry(instrument.to_string()).or_insert(0) += 1;
+    }
+
+    pub async fn get_out_of_session_ticks(&self, instrument: &str) -> u64 {
+        let read_lock = self.out_of_session_ticks.read().await;
+        read_lock.get(instrument).copied().unwrap_or(0)
+    }
+
+    pub async fn record_late_tick(&self, instrument: &str, side: PriceSide) {
+        let mut write_lock = self.late_ticks.write().await;
+        *write_lock
+            .entry((instrument.to_string(), side))
+            .or_insert(0) += 1;
+    }
+
+    pub async fn get_late_ticks(&self, instrument: &str, side: PriceSide) -> u64 {
+        let read_lock = self.late_ticks.read().await;
+        read_lock
+            .get(&(instrument.to_string(), side))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Counters in the Prometheus text exposition format
+    pub async fn to_prometheus(&self) -> String {
+        let mut result = String::new();
+
+        let read_lock = self.late_ticks.read().await;
+        result.push_str(
+            "# HELP candles_late_ticks_total Ticks dropped as older than the lateness window\n",
+        );
+        result.push_str("# TYPE candles_late_ticks_total counter\n");
+
+        for ((instrument, side), count) in read_lock.iter() {
+            result.push_str(&format!(
+                "candles_late_ticks_total{{instrument=\"{}\",side=\"{:?}\"}} {}\n",
+                instrument, side, count
+            ));
+        }
+
+        let read_lock = self.rejected_ticks.read().await;
+        result.push_str(
+            "# HELP candles_rejected_ticks_total Quotes rejected by the tick validation\n",
+        );
+        result.push_str("# TYPE candles_rejected_ticks_total counter\n");
+
+        for ((instrument, reason), count) in read_lock.iter() {
+            result.push_str(&format!(
+                "candles_rejected_ticks_total{{instrument=\"{}\",reason=\"{}\"}} {}\n",
+                instrument,
+                reason.as_str(),
+                count
+            ));
+        }
+
+        let read_lock = self.out_of_session_ticks.read().await;
+        result.push_str("# HELP candles_out_of_session_ticks_total Quotes aggregated outside the trading session\n");
+        result.push_str("# TYPE candles_out_of_session_ticks_total counter\n");
+
+        for (instrument, count) in read_lock.iter() {
+            result.push_str(&format!(
+                "candles_out_of_session_ticks_total{{instrument=\"{}\"}} {}\n",
+                instrument, count
+            ));
+        }
+
+        result
+    }
+}
+
+impl Default for TickMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,11 @@
 use crate::models::CandleType;
 
 pub static PREFIX: &str = "CANDLE";
+pub static SPREAD_PREFIX: &str = "SPREAD";
+/// Prefix of the mid candle tables kept in the bid account
+pub static MID_PREFIX: &str = "MID";
+/// Prefix of the trade candle tables kept in the ask account
+pub static TRADE_PREFIX: &str = "TRADE";
 
 pub fn generate_instrument_name(instrument_id: &str) -> String {
     return format!("{PREFIX}{instrument_id}");
@@ -26,6 +31,36 @@ pub fn get_table_name(candle_type: CandleType, unformatted_instrument_id: &str)
     return format!("{}{}", instrument_id, candle_type as i32);
 }
 
+pub fn get_spread_table_name(candle_type: CandleType, unformatted_instrument_id: &str) -> String {
+    format!(
+        "{SPREAD_PREFIX}{}",
+        get_table_name(candle_type, unformatted_instrument_id)
+    )
+}
+
+/// Candle type and instrument of a candle table name, None for the other tables
+/// and the tables of the sides kept in another account under a prefix
+pub fn parse_candle_table_name(table_name: &str) -> Option<(CandleType, String)> {
+    if [SPREAD_PREFIX, MID_PREFIX, TRADE_PREFIX]
+        .iter()
+        .any(|prefix| table_name.starts_with(prefix))
+    {
+        return None;
+    }
+
+    let (instrument_id, candle_type) = table_name.split_at(table_name.len().checked_sub(1)?);
+    let candle_type = CandleType::try_from(candle_type.parse::<i32>().ok()?).ok()?;
+
+    if instrument_id.is_empty() {
+        return None;
+    }
+
+    Some((
+        candle_type,
+        parse_short_instrument_table_name(instrument_id),
+    ))
+}
+
 pub fn parse_table_name_into_candle_and_instrument(table_name: String) -> (CandleType, String) {
     let candle_type = table_name.parse::<i32>().unwrap();
     let instrument_id = &table_name[0..table_name.len() - 1];
@@ -37,6 +72,11 @@ pub fn parse_table_name_into_candle_and_instrument(table_name: String) -> (Candl
         1 => return (CandleType::Hour, id),
         2 => return (CandleType::Day, id),
         3 => return (CandleType::Month, id),
+        4 => return (CandleType::Minute5, id),
+        5 => return (CandleType::Minute15, id),
+        6 => return (CandleType::Minute30, id),
+        7 => return (CandleType::Hour4, id),
+        8 => return (CandleType::Week, id),
         _ => {
             tracing::error!("Invalid candle type");
             panic!("Invalid candle type")
@@ -0,0 +1,373 @@
+use crate::{
+    caches::{CandlesInstrumentsCache, SpreadsCache},
+    models::{CandleModel, CandleType, DayRollover, PriceSide, SessionCalendar, SpreadCandleModel},
+};
+
+use super::{CandlesStorage, SpreadsStorage};
+
+// periods the storage is searched back for the last candle at first, the window doubles every step
+const LOOKBACK_PERIODS: u64 = 1000;
+const MAX_LOOKBACK_STEPS: u32 = 10;
+
+/// Most periods a single request may fill with synthetic candles
+pub const MAX_FILLED_PERIODS: u64 = 50_000;
+
+/// Candles for the [date_from, date_to) range. The cache holds only the tail of the history,
+/// so the part of the range that is older than the cache is read from the persistent storage.
+pub async fn get_candles_history(
+    cache: &CandlesInstrumentsCache,
+    storage: &dyn CandlesStorage,
+    instrument: &str,
+    candle_type: CandleType,
+    side: PriceSide,
+    date_from: u64,
+    date_to: u64,
+) -> Vec<CandleModel> {
+    let first_cached_date = cache.get_first_date(instrument, candle_type, side).await;
+
+    if let Some(first_cached_date) = first_cached_date {
+        if first_cached_date <= date_from {
+            return cache
+                .get_by_date_range(
+                    instrument.to_string(),
+                    candle_type,
+                    side,
+                    date_from,
+                    date_to,
+                )
+                .await;
+        }
+    }
+
+    let storage_date_to = match first_cached_date {
+        Some(first_cached_date) => u64::min(first_cached_date, date_to),
+        None => date_to,
+    };
+
+    let mut result = storage
+        .get_by_date_range(instrument, side, candle_type, date_from, storage_date_to)
+        .await;
+
+    if storage_date_to < date_to {
+        let cached = cache
+            .get_by_date_range(
+                instrument.to_string(),
+                candle_type,
+                side,
+                storage_date_to,
+                date_to,
+            )
+            .await;
+        result.extend(cached);
+    }
+
+    result
+}
+
+/// Latest candle before `date`, looked up in the persistent storage when the cache has none
+pub async fn get_last_candle_before(
+    cache: &CandlesInstrumentsCache,
+    storage: &dyn CandlesStorage,
+    instrument: &str,
+    candle_type: CandleType,
+    side: PriceSide,
+    date: u64,
+) -> Option<CandleModel> {
+    if let Some(candle) = cache
+        .get_last_before(instrument, candle_type, side, date)
+        .await
+    {
+        return Some(candle);
+    }
+
+    let mut date_to = date;
+    let mut window = candle_type.get_max_duration_sec() * LOOKBACK_PERIODS;
+
+    for _ in 0..MAX_LOOKBACK_STEPS {
+        if date_to == 0 {
+            break;
+        }
+
+        let date_from = date_to.saturating_sub(window);
+        let candle = storage
+            .get_by_date_range(instrument, side, candle_type, date_from, date_to)
+            .await
+            .into_iter()
+            .max_by_key(|candle| candle.datetime);
+
+        if candle.is_some() {
+            return candle;
+        }
+
+        date_to = date_from;
+        window = window.saturating_mul(2);
+    }
+
+    None
+}
+
+/// Adds flat synthetic candles with the previous close for the periods without ticks
+/// in the [date_from, date_to) range. Periods before the first known close stay empty,
+/// periods after the current one are never filled, neither are the periods the session calendar
+/// of the instrument has no session in. Ranges of more than `MAX_FILLED_PERIODS` periods are refused.
+#[allow(clippy::too_many_arguments)]
+pub async fn fill_candles_gaps(
+    cache: &CandlesInstrumentsCache,
+    storage: &dyn CandlesStorage,
+    calendar: Option<&SessionCalendar>,
+    instrument: &str,
+    candle_type: CandleType,
+    side: PriceSide,
+    candles: Vec<CandleModel>,
+    date_from: u64,
+    date_to: u64,
+) -> Result<Vec<CandleModel>, String> {
+    let rollover = cache.get_day_rollover(instrument);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let date_to = u64::min(
+        date_to,
+        rollover.get_next_date(candle_type, rollover.format_date(candle_type, now)),
+    );
+
+    // the whole range is in the future, there is nothing to fill
+    if date_from >= date_to {
+        return Ok(candles);
+    }
+
+    if exceeds_filled_periods(candle_type, rollover, date_from, date_to) {
+        return Err(format!(
+            "Can't fill the gaps of more than {} candles",
+            MAX_FILLED_PERIODS
+        ));
+    }
+
+    let previous =
+        get_last_candle_before(cache, storage, instrument, candle_type, side, date_from).await;
+
+    Ok(fill_gaps(
+        candles,
+        candle_type,
+        rollover,
+        calendar,
+        previous.map(|candle| candle.close),
+        date_from,
+        date_to,
+    ))
+}
+
+fn exceeds_filled_periods(
+    candle_type: CandleType,
+    rollover: DayRollover,
+    date_from: u64,
+    date_to: u64,
+) -> bool {
+    // no period is longer than the max duration, so the range has at least that many of them
+    if date_to.saturating_sub(date_from) / candle_type.get_max_duration_sec() > MAX_FILLED_PERIODS {
+        return true;
+    }
+
+    let mut date = rollover.format_date(candle_type, date_from);
+    for _ in 0..=MAX_FILLED_PERIODS {
+        if date >= date_to {
+            return false;
+        }
+        date = rollover.get_next_date(candle_type, date);
+    }
+
+    true
+}
+
+fn fill_gaps(
+    candles: Vec<CandleModel>,
+    candle_type: CandleType,
+    rollover: DayRollover,
+    calendar: Option<&SessionCalendar>,
+    mut previous_close: Option<f64>,
+    date_from: u64,
+    date_to: u64,
+) -> Vec<CandleModel> {
+    let mut result = Vec::with_capacity(candles.len());
+    let mut candles = candles.into_iter().peekable();
+
+    let mut date = rollover.format_date(candle_type, date_from);
+    if date < date_from {
+        date = rollover.get_next_date(candle_type, date);
+    }
+
+    while date < date_to {
+        let mut has_candle = false;
+
+        while let Some(candle) = candles.next_if(|candle| candle.datetime <= date) {
+            has_candle |= candle.datetime == date;
+            previous_close = Some(candle.close);
+            result.push(candle);
+        }
+
+        let next_date = rollover.get_next_date(candle_type, date);
+        let is_closed = calendar
+            .map(|calendar| !calendar.has_session_between(date, next_date))
+            .unwrap_or(false);
+
+        if !has_candle && !is_closed {
+            if let Some(close) = previous_close {
+                result.push(CandleModel::new_synthetic(date, close));
+            }
+        }
+
+        date = next_date;
+    }
+
+    result.extend(candles);
+    result
+}
+
+/// Spread candles for the [date_from, date_to) range, read the same way as `get_candles_history`
+pub async fn get_spread_history(
+    cache: &SpreadsCache,
+    storage: &dyn SpreadsStorage,
+    instrument: &str,
+    candle_type: CandleType,
+    date_from: u64,
+    date_to: u64,
+) -> Vec<SpreadCandleModel> {
+    let first_cached_date = cache.get_first_date(instrument, candle_type).await;
+
+    if let Some(first_cached_date) = first_cached_date {
+        if first_cached_date <= date_from {
+            return cache
+                .get_by_date_range(instrument, candle_type, date_from, date_to)
+                .await;
+        }
+    }
+
+    let storage_date_to = match first_cached_date {
+        Some(first_cached_date) => u64::min(first_cached_date, date_to),
+        None => date_to,
+    };
+
+    let mut result = storage
+        .get_by_date_range(instrument, candle_type, date_from, storage_date_to)
+        .await;
+
+    if storage_date_to < date_to {
+        let cached = cache
+            .get_by_date_range(instrument, candle_type, storage_date_to, date_to)
+            .await;
+        result.extend(cached);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exceeds_filled_periods, fill_gaps, MAX_FILLED_PERIODS};
+    use crate::models::{
+        CandleModel, CandleType, DayRollover, SessionCalendar, SessionCalendarSettings,
+        TradingSessionSettings,
+    };
+
+    #[test]
+    fn test_fill_gaps() {
+        // ticks on 2022-01-31 and 2022-03-31 only
+        let january = CandleModel::new_from_rate(1640995200, 1643587200, 1.1, None);
+        let march = CandleModel::new_from_rate(1646092800, 1648684800, 1.3, None);
+
+        let result = fill_gaps(
+            vec![january, march],
+            CandleType::Month,
+            DayRollover::UTC,
+            None,
+            Some(1.0),
+            1638316800,
+            1651363200,
+        );
+
+        let dates: Vec<u64> = result.iter().map(|candle| candle.datetime).collect();
+        // December 2021 is filled with the close preceding the range
+        assert_eq!(
+            dates,
+            vec![1638316800, 1640995200, 1643673600, 1646092800, 1648771200]
+        );
+
+        assert!(result[0].synthetic);
+        assert_eq!(result[0].close, 1.0);
+        assert!(!result[1].synthetic);
+        assert!(result[2].synthetic);
+        assert_eq!(result[2].open, 1.1);
+        assert_eq!(result[2].high, 1.1);
+        assert_eq!(result[2].ticks, 0);
+        assert!(!result[3].synthetic);
+        assert!(result[4].synthetic);
+        assert_eq!(result[4].low, 1.3);
+    }
+
+    #[test]
+    fn test_filled_periods_limit() {
+        let date_from = 1662559380;
+        let limit_date = date_from + MAX_FILLED_PERIODS * 60;
+
+        assert!(!exceeds_filled_periods(
+            CandleType::Minute,
+            DayRollover::UTC,
+            date_from,
+            limit_date
+        ));
+        assert!(exceeds_filled_periods(
+            CandleType::Minute,
+            DayRollover::UTC,
+            date_from,
+            limit_date + 60
+        ));
+        assert!(exceeds_filled_periods(
+            CandleType::Minute,
+            DayRollover::UTC,
+            0,
+            u64::MAX
+        ));
+        assert!(!exceeds_filled_periods(
+            CandleType::Month,
+            DayRollover::UTC,
+            0,
+            1662559380
+        ));
+    }
+
+    #[test]
+    fn test_fill_gaps_skips_closed_days() {
+        let calendar = SessionCalendar::new(&SessionCalendarSettings {
+            instruments: vec![],
+            timezone: "UTC".to_string(),
+            sessions: vec![TradingSessionSettings {
+                days: ["Mon", "Tue", "Wed", "Thu", "Fri"]
+                    .iter()
+                    .map(|day| day.to_string())
+                    .collect(),
+                open: "00:00".to_string(),
+                close: "24:00".to_string(),
+            }],
+            holidays: vec![],
+            drop_out_of_session_ticks: false,
+        })
+        .unwrap();
+
+        // ticks on Friday 2022-09-09 and Tuesday 2022-09-13 only
+        let friday = CandleModel::new_from_rate(1662681600, 1662700000, 1.1, None);
+        let tuesday = CandleModel::new_from_rate(1663027200, 1663040000, 1.2, None);
+
+        let result = fill_gaps(
+            vec![friday, tuesday],
+            CandleType::Day,
+            DayRollover::UTC,
+            Some(&calendar),
+            None,
+            1662681600,
+            1663113600,
+        );
+
+        let dates: Vec<u64> = result.iter().map(|candle| candle.datetime).collect();
+        // the weekend stays empty, Monday is filled
+        assert_eq!(dates, vec![1662681600, 1662940800, 1663027200]);
+        assert!(result[1].synthetic);
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{CandleModel, CandleType, PriceSide, SpreadCandleModel};
+
+/// Backend the candles, the spread candles and the instrument metadata are persisted to,
+/// selected in the settings. Only the Azure backend needs the Azure storage accounts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum CandlesStorageType {
+    #[default]
+    Azure,
+    /// Keeps the candles in the process memory, for tests and local runs
+    InMemory,
+    /// Append-only segment files in the `CandlesStoragePath` directory
+    File,
+    /// `candles.db` SQLite database in the `CandlesStoragePath` directory
+    Sqlite,
+}
+
+/// Persistent storage of the candles. Every instrument, side and candle type is a table of its own.
+#[async_trait::async_trait]
+pub trait CandlesStorage: Send + Sync {
+    /// Inserts or replaces the candles, prices are rounded to `digits` when it is known
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        candles: Vec<CandleModel>,
+        digits: Option<u32>,
+    );
+
+    /// Candles of the [date_from, date_to) range sorted by date
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<CandleModel>;
+
+    /// Candles to restore the cache from, at least the ones since `expiration_date`
+    async fn get_async(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        expiration_date: u64,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel>;
+
+    /// Instruments having at least one candle table
+    async fn get_instruments(&self) -> Vec<String>;
+
+    /// Drops the tables of every side and candle type of the instrument
+    async fn delete_tables(&self, instrument: &str);
+}
+
+/// Persistent storage of the spread candles. Every instrument and candle type is a table of its own.
+#[async_trait::async_trait]
+pub trait SpreadsStorage: Send + Sync {
+    /// Inserts or replaces the candles, cached candles hold the whole period
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<SpreadCandleModel>,
+    );
+
+    /// Candles of the [date_from, date_to) range sorted by date
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel>;
+
+    /// Drops the tables of every candle type of the instrument
+    async fn delete_tables(&self, instrument: &str);
+}
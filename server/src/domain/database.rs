@@ -1,165 +1,212 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     sync::Arc,
 };
 
-use async_trait::async_trait;
 use azure_core::Pageable;
 use azure_data_tables::{
     operations::QueryEntityResponse,
     prelude::{TableClient, TableServiceClient},
 };
-use chrono::{Days, Duration, Months, TimeZone, Utc};
+use chrono::{Days, Months, TimeZone, Utc};
 use futures::StreamExt;
 use tokio::sync::RwLock;
 
 use crate::{
     app::AppContext,
-    models::{CandleModel, CandleModelEntity, CandleType},
+    caches::CandlesInstrumentsCache,
+    models::{
+        CandleModel, CandleModelEntity, CandleType, DayRollover, DayRollovers, PriceSide,
+        Timeframe, MAX_KEY_DATE,
+    },
 };
 
-use super::get_table_name;
-
-#[async_trait]
-pub trait Database<T> {
-    async fn read(&self) -> T;
-    async fn increase(&self);
-}
+use super::{
+    get_table_name, parse_candle_table_name, CandlesStorage, InstrumentStorage, MID_PREFIX,
+    TRADE_PREFIX,
+};
 
 pub async fn persist_candles(context: &Arc<AppContext>, latest_timestamp: u64, current_time: u64) {
-    let candle_types = [
-        CandleType::Minute,
-        CandleType::Hour,
-        CandleType::Day,
-        CandleType::Month,
-    ];
+    let candle_types: Vec<CandleType> = context
+        .settings
+        .inner
+        .get_timeframes()
+        .iter()
+        .filter(|timeframe| timeframe.persist)
+        .map(|timeframe| timeframe.candle_type)
+        .collect();
+
+    let _history_guard = context.history_lock.lock().await;
 
     context.instrument_storage.persist().await;
 
-    let instruments_len;
+    persist_cached_candles(
+        &context.cache,
+        context.candles_storage.as_ref(),
+        &context.instrument_storage,
+        &context.day_rollovers,
+        &candle_types,
+        latest_timestamp,
+        current_time,
+    )
+    .await;
+
+    let retired = context.instrument_storage.get_retired().await;
+    let mut spreads_to_persist = Vec::new();
     {
-        instruments_len = context.instrument_storage.instruments.read().await.len();
-    }
-    let storage_len = candle_types.len() * instruments_len;
-    let mut persist_ask = Vec::with_capacity(storage_len);
-    let mut persist_bid = Vec::with_capacity(storage_len);
+        let guard = context.spreads_cache.candles.read().await;
 
-    {
-        let guard = context.cache.ask_candles.read().await;
-
-        for (instrument, candle_cache) in guard.iter() {
-            for candle_type in candle_types {
-                let latest_timestamp = candle_type.format_date_by_type(latest_timestamp);
-                let to_persist_ask =
-                    candle_cache.get_by_date_range(candle_type, latest_timestamp, current_time);
-
-                tracing::info!(
-                    "Persist ask candles for instrument {}; candle_type: {}, amount: {}",
-                    instrument,
-                    candle_type as i32,
-                    to_persist_ask.len()
-                );
-
-                persist_ask.push((instrument.clone(), candle_type, to_persist_ask));
+        for (instrument, caches) in guard.iter() {
+            if retired.contains(instrument) {
+                continue;
             }
-        }
-    }
 
-    {
-        let guard = context.cache.bid_candles.read().await;
-
-        for (instrument, candle_cache) in guard.iter() {
-            for candle_type in candle_types {
-                let latest_timestamp = candle_type.format_date_by_type(latest_timestamp);
-                let to_persist_bid =
-                    candle_cache.get_by_date_range(candle_type, latest_timestamp, current_time);
-
-                tracing::info!(
-                    "Persist bid candles for instrument {}, candle_type: {}, amount: {}",
-                    instrument,
-                    candle_type as i32,
-                    to_persist_bid.len()
-                );
-
-                persist_bid.push((instrument.clone(), candle_type, to_persist_bid));
+            let rollover = context.day_rollovers.get(instrument);
+
+            for (candle_type, cache) in caches.iter() {
+                if !candle_types.contains(candle_type) {
+                    continue;
+                }
+
+                let latest_timestamp = rollover.format_date(*candle_type, latest_timestamp);
+                let candles = cache.get_by_date_range(latest_timestamp, current_time);
+
+                spreads_to_persist.push((instrument.clone(), *candle_type, candles));
             }
         }
     }
-    
-    for ask in persist_ask {
-        let instrument = ask.0;
-        let candle_type = ask.1;
-        let candles = ask.2;
-
-        let _ = context
-            .candles_persistent_azure_storage
-            .bulk_save(&instrument, false, candle_type, candles)
+
+    for (instrument, candle_type, candles) in spreads_to_persist {
+        context
+            .spreads_storage
+            .bulk_save(&instrument, candle_type, candles)
             .await;
     }
+}
 
-    for ask in persist_bid {
-        let instrument = ask.0;
-        let candle_type = ask.1;
-        let candles = ask.2;
-        let _ = context
-            .candles_persistent_azure_storage
-            .bulk_save(&instrument, true, candle_type, candles)
-            .await;
+async fn persist_cached_candles(
+    cache: &CandlesInstrumentsCache,
+    storage: &dyn CandlesStorage,
+    instrument_storage: &InstrumentStorage,
+    day_rollovers: &DayRollovers,
+    candle_types: &[CandleType],
+    latest_timestamp: u64,
+    current_time: u64,
+) {
+    let instruments_len;
+    {
+        instruments_len = instrument_storage.instruments.read().await.len();
+    }
+    let storage_len = candle_types.len() * instruments_len;
+    let retired = instrument_storage.get_retired().await;
+
+    for side in PriceSide::ALL {
+        let mut to_persist = Vec::with_capacity(storage_len);
+
+        {
+            let guard = cache.get_candles(side).read().await;
+
+            for (instrument, candle_cache) in guard.iter() {
+                if retired.contains(instrument) {
+                    continue;
+                }
+
+                let rollover = day_rollovers.get(instrument);
+
+                for candle_type in candle_types.iter().copied() {
+                    let latest_timestamp = rollover.format_date(candle_type, latest_timestamp);
+                    let candles =
+                        candle_cache.get_by_date_range(candle_type, latest_timestamp, current_time);
+
+                    tracing::info!(
+                        "Persist {:?} candles for instrument {}; candle_type: {}, amount: {}",
+                        side,
+                        instrument,
+                        candle_type as i32,
+                        candles.len()
+                    );
+
+                    to_persist.push((instrument.clone(), candle_type, candles));
+                }
+            }
+        }
+
+        for (instrument, candle_type, candles) in to_persist {
+            let digits = instrument_storage.get_digits(&instrument).await;
+
+            if !candles.is_empty() {
+                instrument_storage
+                    .add_candle_type(&instrument, candle_type)
+                    .await;
+            }
+
+            storage
+                .bulk_save(&instrument, side, candle_type, candles, digits)
+                .await;
+        }
     }
 }
 
 pub async fn restore_candles(context: &Arc<AppContext>) -> u64 {
     let mut latest_timestamp = 0;
-    let minute_limit = context.settings.inner.minute_limit as i64;
-    let hour_limit = context.settings.inner.hour_limit as i64;
-    let current_time = chrono::Utc::now();
+    let current_time = chrono::Utc::now().timestamp() as u64;
     let start_time = chrono::Utc::now();
-    let is_bid_ask = [false, true];
-    let candle_types = [
-        (
-            CandleType::Minute,
-            (current_time - Duration::minutes(minute_limit)).timestamp() as u64,
-        ),
-        (
-            CandleType::Hour,
-            (current_time - Duration::hours(hour_limit)).timestamp() as u64,
-        ),
-        (CandleType::Day, u64::MAX),
-        (CandleType::Month, u64::MAX),
-    ];
-
-    let instruments = context.instrument_storage.instruments.read().await;
+    // only persisted candle types can be restored
+    let timeframes: Vec<Timeframe> = context
+        .settings
+        .inner
+        .get_timeframes()
+        .iter()
+        .filter(|timeframe| timeframe.persist)
+        .copied()
+        .collect();
+
+    let instruments = get_instruments_to_restore(
+        context.candles_storage.as_ref(),
+        &context.instrument_storage,
+    )
+    .await;
 
     tracing::info!("Restoring candles for {} instruments", instruments.len());
 
     for instrument in instruments.iter() {
         let start_time = chrono::Utc::now();
-        let instrument = instrument.clone();
-        for is_bid in is_bid_ask {
-            for (candle_type, limit) in candle_types {
-                let mut count = 0;
-                let dbg_str = format!(
-                    "instrument: {}, is:bid: {}, candle_type: {}",
-                    instrument, is_bid, candle_type as i32
-                );
-                tracing::info!("Working with {}", dbg_str);
-
-                let candles = context
-                    .candles_persistent_azure_storage
-                    .get_async(&instrument, is_bid, limit, candle_type)
-                    .await;
-
-                for candle in candles {
-                    latest_timestamp = u64::max(latest_timestamp, candle.datetime);
-                    context
-                        .cache
-                        .init(instrument.clone(), is_bid, candle_type, candle)
-                        .await;
+        let rollover = context.day_rollovers.get(instrument);
+
+        latest_timestamp = u64::max(
+            latest_timestamp,
+            restore_instrument_candles(
+                &context.cache,
+                context.candles_storage.as_ref(),
+                &context.instrument_storage,
+                rollover,
+                &timeframes,
+                instrument,
+                current_time,
+            )
+            .await,
+        );
 
-                    count += 1;
-                }
+        for timeframe in timeframes.iter() {
+            let candle_type = timeframe.candle_type;
+            let date_from = timeframe.get_limit_date(current_time, rollover);
+            let spreads = context
+                .spreads_storage
+                .get_by_date_range(instrument, candle_type, date_from, current_time + 1)
+                .await;
 
-                tracing::info!("{}; Processed: {}", dbg_str, count);
+            tracing::info!(
+                "instrument: {}, candle_type: {}; Processed spreads: {}",
+                instrument,
+                candle_type as i32,
+                spreads.len()
+            );
+
+            for spread in spreads {
+                context
+                    .spreads_cache
+                    .init(instrument, candle_type, spread)
+                    .await;
             }
         }
 
@@ -180,48 +227,177 @@ pub async fn restore_candles(context: &Arc<AppContext>) -> u64 {
     return latest_timestamp;
 }
 
+/// Active instruments of the instrument storage. The tables of the candles storage are only
+/// reported: the Azure table names drop the dots of the instrument and can't be mapped back.
+async fn get_instruments_to_restore(
+    storage: &dyn CandlesStorage,
+    instrument_storage: &InstrumentStorage,
+) -> Vec<String> {
+    let known = instrument_storage.get_instruments().await;
+
+    for instrument in storage.get_instruments().await {
+        let is_known = known
+            .iter()
+            .any(|known| known == &instrument || known.replace('.', "") == instrument);
+
+        if !is_known {
+            tracing::warn!(
+                "Candle tables of {} don't match any known instrument, they are not restored",
+                instrument
+            );
+        }
+    }
+
+    instrument_storage.get_active_instruments().await
+}
+
+/// Returns the date of the latest restored candle
+async fn restore_instrument_candles(
+    cache: &CandlesInstrumentsCache,
+    storage: &dyn CandlesStorage,
+    instrument_storage: &InstrumentStorage,
+    rollover: DayRollover,
+    timeframes: &[Timeframe],
+    instrument: &str,
+    current_time: u64,
+) -> u64 {
+    let mut latest_timestamp = 0;
+    let candle_types: Vec<(CandleType, u64)> = timeframes
+        .iter()
+        .map(|timeframe| {
+            (
+                timeframe.candle_type,
+                timeframe.get_limit_date(current_time, rollover),
+            )
+        })
+        .collect();
+
+    for side in PriceSide::ALL {
+        for (candle_type, limit) in candle_types.iter().copied() {
+            let mut count = 0;
+            let dbg_str = format!(
+                "instrument: {}, side: {:?}, candle_type: {}",
+                instrument, side, candle_type as i32
+            );
+            tracing::info!("Working with {}", dbg_str);
+
+            let candles = storage
+                .get_async(instrument, side, limit, candle_type)
+                .await;
+
+            for candle in candles {
+                if candle.datetime < limit {
+                    continue;
+                }
+
+                latest_timestamp = u64::max(latest_timestamp, candle.datetime);
+                cache
+                    .init(instrument.to_string(), side, candle_type, candle)
+                    .await;
+
+                count += 1;
+            }
+
+            if count > 0 {
+                instrument_storage
+                    .add_candle_type(instrument, candle_type)
+                    .await;
+            }
+
+            tracing::info!("{}; Processed: {}", dbg_str, count);
+        }
+    }
+
+    latest_timestamp
+}
+
+/// Table service of a price side, the table names are prefixed when the account is shared
+struct SideAccount {
+    table_service: Arc<TableServiceClient>,
+    cloud_tables: RwLock<HashMap<String, Arc<TableClient>>>,
+    table_prefix: &'static str,
+}
+
+impl SideAccount {
+    fn new(table_service: Arc<TableServiceClient>, table_prefix: &'static str) -> Self {
+        Self {
+            table_service,
+            cloud_tables: RwLock::new(HashMap::new()),
+            table_prefix,
+        }
+    }
+
+    fn get_table_name(&self, candle_type: CandleType, instrument: &str) -> String {
+        format!(
+            "{}{}",
+            self.table_prefix,
+            get_table_name(candle_type, instrument)
+        )
+    }
+
+    /// Instrument of a candle table of the side, None for the tables of the other sides
+    fn parse_table_name(&self, table_name: &str) -> Option<String> {
+        let table_name = table_name.strip_prefix(self.table_prefix)?;
+
+        parse_candle_table_name(table_name).map(|(_, instrument)| instrument)
+    }
+}
+
 pub struct CandlesPersistentAzureStorage {
-    table_service_ask: Arc<TableServiceClient>,
-    table_service_bid: Arc<TableServiceClient>,
-    cloud_tables_bids: Arc<RwLock<HashMap<String, Arc<TableClient>>>>,
-    cloud_tables_asks: Arc<RwLock<HashMap<String, Arc<TableClient>>>>,
+    ask: SideAccount,
+    bid: SideAccount,
+    mid: SideAccount,
+    trade: SideAccount,
+    day_rollovers: Arc<DayRollovers>,
 }
 
 impl CandlesPersistentAzureStorage {
+    /// The mid candles are kept in the bid account when there is no mid one
     pub fn new(
         table_service_ask: Arc<TableServiceClient>,
         table_service_bid: Arc<TableServiceClient>,
+        table_service_mid: Option<Arc<TableServiceClient>>,
+        table_service_trade: Option<Arc<TableServiceClient>>,
+        day_rollovers: Arc<DayRollovers>,
     ) -> Self {
+        let mid = match table_service_mid {
+            Some(table_service_mid) => SideAccount::new(table_service_mid, ""),
+            None => SideAccount::new(table_service_bid.clone(), MID_PREFIX),
+        };
+        let trade = match table_service_trade {
+            Some(table_service_trade) => SideAccount::new(table_service_trade, ""),
+            None => SideAccount::new(table_service_ask.clone(), TRADE_PREFIX),
+        };
+
         Self {
-            table_service_ask,
-            table_service_bid,
-            cloud_tables_bids: Arc::new(RwLock::new(HashMap::new())),
-            cloud_tables_asks: Arc::new(RwLock::new(HashMap::new())),
+            ask: SideAccount::new(table_service_ask, ""),
+            bid: SideAccount::new(table_service_bid, ""),
+            mid,
+            trade,
+            day_rollovers,
+        }
+    }
+
+    fn get_account(&self, side: PriceSide) -> &SideAccount {
+        match side {
+            PriceSide::Bid => &self.bid,
+            PriceSide::Ask => &self.ask,
+            PriceSide::Mid => &self.mid,
+            PriceSide::Trade => &self.trade,
         }
     }
 
     async fn get_azure_table_storage(
         &self,
         instrument: &str,
-        bid: bool,
+        side: PriceSide,
         candle_type: CandleType,
     ) -> Arc<TableClient> {
-        let table_name = get_table_name(candle_type, instrument);
-
-        let account = if bid {
-            self.table_service_bid.clone()
-        } else {
-            self.table_service_ask.clone()
-        };
-
-        let cloud_tables = if bid {
-            self.cloud_tables_bids.clone()
-        } else {
-            self.cloud_tables_asks.clone()
-        };
+        let account = self.get_account(side);
+        let table_name = account.get_table_name(candle_type, instrument);
 
         {
-            let table_storage = cloud_tables.read().await;
+            let table_storage = account.cloud_tables.read().await;
             let table_storage = table_storage.get(&table_name);
 
             if let Some(table) = table_storage {
@@ -229,20 +405,105 @@ impl CandlesPersistentAzureStorage {
             }
         }
 
-        let table_storage = Arc::new(account.table_client(&table_name));
+        let table_storage = Arc::new(account.table_service.table_client(&table_name));
         let _ = table_storage.create().await;
         let return_val = table_storage.clone();
-        cloud_tables.write().await.insert(table_name, table_storage);
+        account
+            .cloud_tables
+            .write()
+            .await
+            .insert(table_name, table_storage);
 
         return return_val;
     }
 
-    pub async fn bulk_save(
+    async fn get_all(
         &self,
         instrument: &str,
-        bid: bool,
+        side: PriceSide,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel> {
+        let rollover = self.day_rollovers.get(instrument);
+        let mut result = Vec::with_capacity(1024);
+        let table_storage = self
+            .get_azure_table_storage(instrument, side, candle_type)
+            .await;
+
+        let mut stream: Pageable<QueryEntityResponse<CandleModelEntity>, _> =
+            table_storage.query().into_stream();
+        while let Some(entity) = stream.next().await {
+            let entity = entity.unwrap();
+
+            for candle in entity.entities {
+                let candles = candle.get_candles(candle_type, rollover);
+
+                for candle in candles.into_iter() {
+                    result.push(candle.1);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl CandlesStorage for CandlesPersistentAzureStorage {
+    async fn get_instruments(&self) -> Vec<String> {
+        let mut result = HashSet::new();
+
+        for side in PriceSide::ALL {
+            let account = self.get_account(side);
+            let mut stream = account.table_service.list().into_stream();
+
+            while let Some(response) = stream.next().await {
+                match response {
+                    Ok(response) => {
+                        result.extend(
+                            response
+                                .tables
+                                .iter()
+                                .filter_map(|table| account.parse_table_name(&table.name)),
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!("Error while listing candle tables; Err: {:?}", err);
+                        break;
+                    }
+                }
+            }
+        }
+
+        result.into_iter().collect()
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        for side in PriceSide::ALL {
+            let account = self.get_account(side);
+
+            for candle_type in CandleType::ALL {
+                let table_name = account.get_table_name(candle_type, instrument);
+                account.cloud_tables.write().await.remove(&table_name);
+
+                if let Err(err) = account
+                    .table_service
+                    .table_client(&table_name)
+                    .delete()
+                    .await
+                {
+                    tracing::warn!("Can't delete candle table {}: {:?}", table_name, err);
+                }
+            }
+        }
+    }
+
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        side: PriceSide,
         candle_type: CandleType,
         candles: Vec<CandleModel>,
+        digits: Option<u32>,
     ) {
         /* tracing::info!(
             "Saving BULK {} {} {} candles {}",
@@ -252,8 +513,9 @@ impl CandlesPersistentAzureStorage {
             candles.len()
         ); */
         let table_storage = self
-            .get_azure_table_storage(instrument, bid, candle_type)
+            .get_azure_table_storage(instrument, side, candle_type)
             .await;
+        let rollover = self.day_rollovers.get(instrument);
 
         let mut entities_by_partition_rows_dict: HashMap<
             String,
@@ -262,8 +524,9 @@ impl CandlesPersistentAzureStorage {
 
         for candle in candles {
             let partition_key =
-                CandleModelEntity::generate_partition_key(candle.datetime, candle_type);
-            let row_key = CandleModelEntity::generate_row_key(candle.datetime, candle_type);
+                CandleModelEntity::generate_partition_key(candle.datetime, candle_type, rollover);
+            let row_key =
+                CandleModelEntity::generate_row_key(candle.datetime, candle_type, rollover);
 
             if !entities_by_partition_rows_dict.contains_key(&partition_key) {
                 let map: HashMap<String, CandleModelEntity> = HashMap::new();
@@ -271,37 +534,13 @@ impl CandlesPersistentAzureStorage {
             }
 
             // get row from Dict, otherwise get it from DB
-            let entity =
-                if let Some(partition) = entities_by_partition_rows_dict.get_mut(&partition_key) {
-                    if let Some(entity) = partition.get_mut(&row_key) {
-                        entity
-                    } else {
-                        let entity = CandleModelEntity::create(candle_type, candle.clone());
-
-                        let entry = entities_by_partition_rows_dict
-                            .get_mut(&partition_key)
-                            .unwrap()
-                            .entry(row_key);
-
-                        let val = match entry {
-                            Entry::Occupied(o) => o.into_mut(),
-                            Entry::Vacant(v) => v.insert(entity),
-                        };
-
-                        val
-                    }
+            let entity = if let Some(partition) =
+                entities_by_partition_rows_dict.get_mut(&partition_key)
+            {
+                if let Some(entity) = partition.get_mut(&row_key) {
+                    entity
                 } else {
-                    let get = table_storage
-                        .partition_key_client(&partition_key)
-                        .entity_client(&row_key)
-                        .unwrap()
-                        .get()
-                        .await;
-
-                    let entity = match get {
-                        Ok(ent) => ent.entity,
-                        Err(_) => CandleModelEntity::create(candle_type, candle.clone()),
-                    };
+                    let entity = CandleModelEntity::create(candle_type, candle.clone(), rollover);
 
                     let entry = entities_by_partition_rows_dict
                         .get_mut(&partition_key)
@@ -314,9 +553,34 @@ impl CandlesPersistentAzureStorage {
                     };
 
                     val
+                }
+            } else {
+                let get = table_storage
+                    .partition_key_client(&partition_key)
+                    .entity_client(&row_key)
+                    .unwrap()
+                    .get()
+                    .await;
+
+                let entity = match get {
+                    Ok(ent) => ent.entity,
+                    Err(_) => CandleModelEntity::create(candle_type, candle.clone(), rollover),
+                };
+
+                let entry = entities_by_partition_rows_dict
+                    .get_mut(&partition_key)
+                    .unwrap()
+                    .entry(row_key);
+
+                let val = match entry {
+                    Entry::Occupied(o) => o.into_mut(),
+                    Entry::Vacant(v) => v.insert(entity),
                 };
 
-            let mut candles_dict = entity.get_candles(candle_type);
+                val
+            };
+
+            let mut candles_dict = entity.get_candles(candle_type, rollover);
 
             match candles_dict.entry(candle.datetime) {
                 std::collections::btree_map::Entry::Vacant(_) => {
@@ -328,10 +592,15 @@ impl CandlesPersistentAzureStorage {
                     val.close = candle.close;
                     val.high = candle.high;
                     val.low = candle.low;
+                    val.ticks = candle.ticks;
+                    val.volume = candle.volume;
+                    val.vwap = candle.vwap;
+                    val.open_time = candle.open_time;
+                    val.close_time = candle.close_time;
                 }
             }
 
-            CandleModelEntity::set_candles(entity, candles_dict, 0, candle_type);
+            CandleModelEntity::set_candles(entity, candles_dict, digits, candle_type, rollover);
         }
 
         for (partition_key, values) in entities_by_partition_rows_dict.into_iter() {
@@ -362,9 +631,9 @@ impl CandlesPersistentAzureStorage {
                     match res {
                         Ok(response) => {
                             tracing::trace!(
-                                "SAVED! {} {} {}; headers: {:?}  ", //RESPONSES: {:?}",
+                                "SAVED! {} {:?} {}; headers: {:?}  ", //RESPONSES: {:?}",
                                 instrument,
-                                bid,
+                                side,
                                 candle_type as i32,
                                 response.common_storage_response_headers,
                                 //response.operation_responses
@@ -410,115 +679,306 @@ impl CandlesPersistentAzureStorage {
         // bulk update is allowed only whithin the same partition
     }
 
-    pub async fn get_async(
+    async fn get_by_date_range(
         &self,
         instrument: &str,
-        bid: bool,
-        expiration_date: u64,
+        side: PriceSide,
         candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
     ) -> Vec<CandleModel> {
-        if candle_type == CandleType::Day || candle_type == CandleType::Month {
-            let mut result = Vec::with_capacity(1024);
-            let table_storage = self
-                .get_azure_table_storage(instrument, bid, candle_type)
-                .await;
-
-            // for these types simply iterate through all records
-            let mut stream: Pageable<QueryEntityResponse<CandleModelEntity>, _> =
-                table_storage.query().into_stream();
-            while let Some(entity) = stream.next().await {
-                let entity = entity.unwrap();
+        let mut result = Vec::new();
+        let table_storage = self
+            .get_azure_table_storage(instrument, side, candle_type)
+            .await;
+        let rollover = self.day_rollovers.get(instrument);
 
-                for candle in entity.entities {
-                    let candles = candle.get_candles(candle_type);
+        // partition keys are date based, so they sort the same way as the dates
+        let filter = format!(
+            "PartitionKey ge '{}' and PartitionKey le '{}'",
+            CandleModelEntity::generate_partition_key(
+                u64::min(date_from, MAX_KEY_DATE),
+                candle_type,
+                rollover,
+            ),
+            CandleModelEntity::generate_partition_key(
+                u64::min(date_to, MAX_KEY_DATE),
+                candle_type,
+                rollover,
+            ),
+        );
 
-                    for candle in candles.into_iter() {
-                        result.push(candle.1);
+        let mut stream: Pageable<QueryEntityResponse<CandleModelEntity>, _> =
+            table_storage.query().filter(filter).into_stream();
+
+        while let Some(entity) = stream.next().await {
+            match entity {
+                Ok(entity) => {
+                    for entity in entity.entities {
+                        let candles = entity.get_candles(candle_type, rollover);
+                        result.extend(candles.into_values().filter(|candle| {
+                            candle.datetime >= date_from && candle.datetime < date_to
+                        }));
                     }
                 }
+                Err(err) => {
+                    tracing::error!("Error while reading candles from Azure; Err: {:?}", err);
+                }
             }
+        }
 
-            result
-        } else {
-            let mut result = Vec::with_capacity(239_416);
-            // prepare list for getting data by partitons
-            let mut partitions_key_list = vec![CandleModelEntity::generate_partition_key(
-                expiration_date,
-                candle_type,
-            )];
-
-            let mut next_partition_date = expiration_date;
-
-            'outer: loop {
-                let date_time = Utc
-                    .timestamp_millis_opt((next_partition_date * 1000) as i64)
-                    .unwrap();
-                // fill up list with partition keys based on Key granularity
-                match candle_type {
-                    CandleType::Minute => {
-                        next_partition_date =
-                            (date_time.checked_add_days(Days::new(1)).unwrap()).timestamp() as u64;
-                    }
-                    CandleType::Hour => {
-                        next_partition_date =
-                            (date_time.checked_add_months(Months::new(1)).unwrap()).timestamp()
-                                as u64;
-                    }
-                    CandleType::Day => todo!(),
-                    CandleType::Month => todo!(),
-                };
+        result.sort_by_key(|candle| candle.datetime);
+        result
+    }
 
-                partitions_key_list.push(CandleModelEntity::generate_partition_key(
-                    next_partition_date,
-                    candle_type,
-                ));
-                let current_time = chrono::Utc::now().timestamp() as u64;
-                if next_partition_date > current_time {
-                    break 'outer;
-                }
+    async fn get_async(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        expiration_date: u64,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel> {
+        let rollover = self.day_rollovers.get(instrument);
+
+        // partitions of the daily types hold a year or more, so their tables are read whole
+        let (partition_days, partition_months) = match candle_type {
+            CandleType::Minute => (1, 0),
+            CandleType::Hour
+            | CandleType::Minute5
+            | CandleType::Minute15
+            | CandleType::Minute30 => (0, 1),
+            CandleType::Hour4 => (0, 12),
+            CandleType::Day | CandleType::Week | CandleType::Month => {
+                return self.get_all(instrument, side, candle_type).await;
             }
+        };
 
-            let table_name = get_table_name(candle_type, instrument);
-            let table_storage = self
-                .get_azure_table_storage(instrument, bid, candle_type)
-                .await;
+        let mut result = Vec::with_capacity(239_416);
+        // prepare list for getting data by partitons
+        let mut partitions_key_list = vec![CandleModelEntity::generate_partition_key(
+            expiration_date,
+            candle_type,
+            rollover,
+        )];
+
+        let mut next_partition_date = expiration_date;
+
+        'outer: loop {
+            let date_time = Utc
+                .timestamp_millis_opt((next_partition_date * 1000) as i64)
+                .unwrap();
+            // fill up list with partition keys based on Key granularity
+            next_partition_date = date_time
+                .checked_add_days(Days::new(partition_days))
+                .and_then(|date_time| date_time.checked_add_months(Months::new(partition_months)))
+                .unwrap()
+                .timestamp() as u64;
+
+            partitions_key_list.push(CandleModelEntity::generate_partition_key(
+                next_partition_date,
+                candle_type,
+                rollover,
+            ));
+            let current_time = chrono::Utc::now().timestamp() as u64;
+            if next_partition_date > current_time {
+                break 'outer;
+            }
+        }
+
+        let table_name = self
+            .get_account(side)
+            .get_table_name(candle_type, instrument);
+        let table_storage = self
+            .get_azure_table_storage(instrument, side, candle_type)
+            .await;
+
+        tracing::info!("Got table storage!");
+
+        // iterate by partitions
+        for partition_key in partitions_key_list {
+            let mut count = 0;
+            tracing::info!(
+                "Table: {}; Getting partition: {}",
+                table_name,
+                partition_key
+            );
+            let mut stream: Pageable<QueryEntityResponse<CandleModelEntity>, _> = table_storage
+                .query()
+                .initial_partition_key(&partition_key)
+                .into_stream();
 
-            tracing::info!("Got table storage!");
-
-            // iterate by partitions
-            for partition_key in partitions_key_list {
-                let mut count = 0;
-                tracing::info!(
-                    "Table: {}; Getting partition: {}",
-                    table_name,
-                    partition_key
-                );
-                let mut stream: Pageable<QueryEntityResponse<CandleModelEntity>, _> = table_storage
-                    .query()
-                    .initial_partition_key(&partition_key)
-                    .into_stream();
-
-                while let Some(entity) = stream.next().await {
-                    if let Ok(entity) = entity {
-                        for candle in entity.entities {
-                            let candles = candle.get_candles(candle_type);
-                            for candle in candles.into_iter() {
-                                count += 1;
-                                result.push(candle.1);
-                            }
+            while let Some(entity) = stream.next().await {
+                if let Ok(entity) = entity {
+                    for candle in entity.entities {
+                        let candles = candle.get_candles(candle_type, rollover);
+                        for candle in candles.into_iter() {
+                            count += 1;
+                            result.push(candle.1);
                         }
                     }
                 }
-
-                tracing::info!(
-                    "For table: {}; For partition: {}; Got {} candles",
-                    table_name,
-                    partition_key,
-                    count
-                );
             }
 
-            result
+            tracing::info!(
+                "For table: {}; For partition: {}; Got {} candles",
+                table_name,
+                partition_key,
+                count
+            );
         }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use azure_data_tables::prelude::TableServiceClient;
+    use azure_storage::StorageCredentials;
+
+    use crate::{
+        caches::{CandlesInstrumentsCache, TickMetrics},
+        domain::{CandlesStorage, InMemoryCandlesStorage, InstrumentStorage},
+        models::{CandleType, CandlesBidAsk, DayRollover, DayRollovers, PriceSide, Timeframe},
+    };
+
+    use super::{
+        get_instruments_to_restore, persist_cached_candles, restore_instrument_candles, SideAccount,
+    };
+
+    fn create_table_service() -> Arc<TableServiceClient> {
+        Arc::new(TableServiceClient::new(
+            "test",
+            StorageCredentials::Key("test".to_string(), "dGVzdA==".to_string()),
+        ))
+    }
+
+    fn create_cache(timeframes: &[Timeframe]) -> CandlesInstrumentsCache {
+        CandlesInstrumentsCache::new(
+            timeframes.to_vec(),
+            300,
+            Arc::new(TickMetrics::new()),
+            Arc::new(DayRollovers::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_persist_restore_roundtrip() {
+        let timeframes = [
+            Timeframe::new(CandleType::Minute, None),
+            Timeframe::new(CandleType::Day, None),
+        ];
+        let candle_types = [CandleType::Minute, CandleType::Day];
+        let instrument_storage =
+            InstrumentStorage::new(Arc::new(InMemoryCandlesStorage::new()), HashMap::new());
+        let storage = InMemoryCandlesStorage::new();
+
+        instrument_storage.record_tick("EURUSD", 1662559404).await;
+
+        let cache = create_cache(&timeframes);
+        cache
+            .update(vec![
+                CandlesBidAsk {
+                    date: 1662559404,
+                    instrument: "EURUSD".to_string(),
+                    bid: 1.001,
+                    ask: 1.002,
+                    volume: None,
+                },
+                CandlesBidAsk {
+                    date: 1662559474,
+                    instrument: "EURUSD".to_string(),
+                    bid: 1.003,
+                    ask: 1.004,
+                    volume: None,
+                },
+                CandlesBidAsk {
+                    date: 1662559474,
+                    instrument: "BTCUSD".to_string(),
+                    bid: 20000.1,
+                    ask: 20000.2,
+                    volume: None,
+                },
+            ])
+            .await;
+
+        persist_cached_candles(
+            &cache,
+            &storage,
+            &instrument_storage,
+            &DayRollovers::default(),
+            &candle_types,
+            0,
+            1662560000,
+        )
+        .await;
+
+        let instruments = get_instruments_to_restore(&storage, &instrument_storage).await;
+        assert_eq!(instruments, vec!["EURUSD".to_string()]);
+
+        let restored = create_cache(&timeframes);
+        let latest_timestamp = restore_instrument_candles(
+            &restored,
+            &storage,
+            &instrument_storage,
+            DayRollover::UTC,
+            &timeframes,
+            "EURUSD",
+            1662560000,
+        )
+        .await;
+        assert_eq!(latest_timestamp, 1662559440);
+
+        for candle_type in candle_types {
+            let expected = cache
+                .get_by_date_range(
+                    "EURUSD".to_string(),
+                    candle_type,
+                    PriceSide::Bid,
+                    0,
+                    u64::MAX,
+                )
+                .await;
+            let actual = restored
+                .get_by_date_range(
+                    "EURUSD".to_string(),
+                    candle_type,
+                    PriceSide::Bid,
+                    0,
+                    u64::MAX,
+                )
+                .await;
+
+            assert!(!expected.is_empty());
+            assert_eq!(actual.len(), expected.len());
+            for (actual, expected) in actual.iter().zip(expected.iter()) {
+                assert_eq!(actual.datetime, expected.datetime);
+                assert_eq!(actual.open, expected.open);
+                assert_eq!(actual.close, expected.close);
+            }
+        }
+
+        storage.delete_tables("EURUSD").await;
+        storage.delete_tables("BTCUSD").await;
+        assert!(storage.get_instruments().await.is_empty());
+    }
+
+    #[test]
+    fn test_shared_account_table_names() {
+        let bid = SideAccount::new(create_table_service(), "");
+        let mid = SideAccount::new(create_table_service(), "MID");
+
+        assert_eq!(mid.get_table_name(CandleType::Hour, "EURUSD"), "MIDEURUSD1");
+        assert_eq!(
+            mid.parse_table_name("MIDEURUSD1"),
+            Some("EURUSD".to_string())
+        );
+        assert_eq!(mid.parse_table_name("EURUSD1"), None);
+        assert_eq!(bid.parse_table_name("MIDEURUSD1"), None);
+        assert_eq!(bid.parse_table_name("EURUSD1"), Some("EURUSD".to_string()));
+        assert_eq!(bid.parse_table_name("SPREADEURUSD1"), None);
+        assert_eq!(bid.parse_table_name("TRADEEURUSD1"), None);
     }
 }
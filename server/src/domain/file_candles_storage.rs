@@ -0,0 +1,891 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use crate::models::{
+    format_price, CandleModel, CandleModelEntity, CandleType, DayRollovers, PriceSide,
+    SpreadCandleModel, MAX_KEY_DATE,
+};
+
+use super::{
+    instrument_storage::InstrumentStorageEntity, CandlesStorage, InstrumentMetadata,
+    InstrumentMetadataStorage, SpreadsStorage,
+};
+
+const SEGMENT_EXTENSION: &str = "seg";
+const COMPACTION_EXTENSION: &str = "seg.tmp";
+const SPREAD_DIR: &str = "spread";
+const INSTRUMENTS_FILE: &str = "instruments.jsonl";
+// a segment is compacted once it holds this many times more records than candles
+const COMPACTION_RATIO: usize = 4;
+const COMPACTION_MIN_RECORDS: usize = 256;
+// the states are read from the segments again after they are forgotten
+const MAX_TRACKED_SEGMENTS: usize = 10_000;
+
+/// Candles in append-only segment files on the local disk:
+/// `{root}/{instrument}/{candle type}/{side}/{partition key}.seg`,
+/// the spread candles in `{root}/{instrument}/spread/{candle type}/{partition key}.seg`
+/// and the instrument metadata in `{root}/instruments.jsonl`.
+/// Partition keys are the ones of `CandleModelEntity`. Every save appends a record per candle,
+/// the latest record of a date wins on read.
+pub struct FileCandlesStorage {
+    root: PathBuf,
+    day_rollovers: Arc<DayRollovers>,
+    /// States of the segments saved to, so a save doesn't read its segment again.
+    /// Doubles as the write lock of the storage.
+    segments: Mutex<HashMap<PathBuf, SegmentState>>,
+}
+
+impl FileCandlesStorage {
+    pub fn new(root: impl Into<PathBuf>, day_rollovers: Arc<DayRollovers>) -> Self {
+        Self {
+            root: root.into(),
+            day_rollovers,
+            segments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_instrument_dir(&self, instrument: &str) -> Option<PathBuf> {
+        if instrument.is_empty()
+            || instrument == "."
+            || instrument == ".."
+            || instrument.contains(['/', '\\'])
+        {
+            tracing::warn!(
+                "Instrument {} can't be used as a directory name",
+                instrument
+            );
+            return None;
+        }
+
+        Some(self.root.join(instrument))
+    }
+
+    fn get_table_dir(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+    ) -> Option<PathBuf> {
+        let dir = self.get_instrument_dir(instrument)?;
+
+        Some(
+            dir.join((candle_type as i32).to_string())
+                .join((side as i32).to_string()),
+        )
+    }
+
+    fn get_spread_table_dir(&self, instrument: &str, candle_type: CandleType) -> Option<PathBuf> {
+        let dir = self.get_instrument_dir(instrument)?;
+
+        Some(dir.join(SPREAD_DIR).join((candle_type as i32).to_string()))
+    }
+
+    /// Partition keys of the table within [key_from, key_to], sorted
+    async fn get_partition_keys(&self, dir: &Path, key_from: &str, key_to: &str) -> Vec<String> {
+        let mut result = Vec::new();
+
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return result,
+            Err(err) => {
+                tracing::error!("Can't read candle segments of {:?}; Err: {:?}", dir, err);
+                return result;
+            }
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+
+            if let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if key >= key_from && key <= key_to {
+                    result.push(key.to_string());
+                }
+            }
+        }
+
+        result.sort();
+        result
+    }
+
+    async fn read_table<T: SegmentRecord>(
+        &self,
+        dir: &Path,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<T> {
+        let rollover = self.day_rollovers.get(instrument);
+        let key_from = CandleModelEntity::generate_partition_key(
+            u64::min(date_from, MAX_KEY_DATE),
+            candle_type,
+            rollover,
+        );
+        let key_to = CandleModelEntity::generate_partition_key(
+            u64::min(date_to, MAX_KEY_DATE),
+            candle_type,
+            rollover,
+        );
+
+        let mut result = Vec::new();
+
+        for key in self.get_partition_keys(dir, &key_from, &key_to).await {
+            let segment: Segment<T> = read_segment(&get_segment_path(dir, &key)).await;
+
+            result.extend(segment.candles.into_values().filter(|candle| {
+                candle.get_datetime() >= date_from && candle.get_datetime() < date_to
+            }));
+        }
+
+        result
+    }
+
+    async fn save_table<T: SegmentRecord>(
+        &self,
+        dir: &Path,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<T>,
+        digits: Option<u32>,
+    ) {
+        if candles.is_empty() {
+            return;
+        }
+
+        let rollover = self.day_rollovers.get(instrument);
+
+        let mut by_partition: BTreeMap<String, String> = BTreeMap::new();
+        for candle in candles {
+            let partition_key = CandleModelEntity::generate_partition_key(
+                candle.get_datetime(),
+                candle_type,
+                rollover,
+            );
+            let records = by_partition.entry(partition_key).or_default();
+            records.push_str(&candle.to_record(digits));
+            records.push('\n');
+        }
+
+        let mut segments = self.segments.lock().await;
+
+        if let Err(err) = fs::create_dir_all(dir).await {
+            tracing::error!("Can't create candle directory {:?}; Err: {:?}", dir, err);
+            return;
+        }
+
+        for (partition_key, records) in by_partition {
+            let path = get_segment_path(dir, &partition_key);
+            let mut state = match segments.remove(&path) {
+                Some(state) => state,
+                None => SegmentState::new(&read_segment::<T>(&path).await),
+            };
+
+            // a failed write leaves the segment in an unknown state, it is read again on the next save
+            if let Err(err) = append_segment(&path, &state, records.as_bytes()).await {
+                tracing::error!("Error while saving candles to {:?}; Err: {:?}", path, err);
+                continue;
+            }
+
+            if state.len == 0 {
+                // the new segment file is not durable until its directory entry is
+                if let Err(err) = sync_dir(dir).await {
+                    tracing::error!("Can't sync candle directory {:?}; Err: {:?}", dir, err);
+                }
+            }
+
+            state.len = state.valid_len + records.len() as u64;
+            state.valid_len = state.len;
+            for candle in records.lines().filter_map(T::parse_record) {
+                state.dates.insert(candle.get_datetime());
+                state.records += 1;
+            }
+
+            if state.records >= COMPACTION_MIN_RECORDS
+                && state.records > state.dates.len() * COMPACTION_RATIO
+            {
+                let segment: Segment<T> = read_segment(&path).await;
+
+                match compact_segment(&path, &segment.candles).await {
+                    Ok(len) => {
+                        state = SegmentState {
+                            dates: segment.candles.keys().copied().collect(),
+                            records: segment.candles.len(),
+                            valid_len: len,
+                            len,
+                        }
+                    }
+                    Err(err) => {
+                        tracing::error!("Error while compacting {:?}; Err: {:?}", path, err)
+                    }
+                }
+            }
+
+            if segments.len() >= MAX_TRACKED_SEGMENTS {
+                segments.clear();
+            }
+            segments.insert(path, state);
+        }
+    }
+
+    /// Removes the directories and the instrument directory once nothing else is left in it
+    async fn remove_dirs(&self, instrument: &str, dirs: Vec<PathBuf>) {
+        let instrument_dir = match self.get_instrument_dir(instrument) {
+            Some(dir) => dir,
+            None => return,
+        };
+
+        let mut segments = self.segments.lock().await;
+        segments.retain(|path, _| !dirs.iter().any(|dir| path.starts_with(dir)));
+
+        for dir in dirs {
+            match fs::remove_dir_all(&dir).await {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => tracing::warn!("Can't delete candle directory {:?}: {:?}", dir, err),
+            }
+        }
+
+        // fails while the other tables of the instrument are there
+        let _ = fs::remove_dir(&instrument_dir).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl CandlesStorage for FileCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        candles: Vec<CandleModel>,
+        digits: Option<u32>,
+    ) {
+        if let Some(dir) = self.get_table_dir(instrument, side, candle_type) {
+            self.save_table(&dir, instrument, candle_type, candles, digits)
+                .await;
+        }
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<CandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        match self.get_table_dir(instrument, side, candle_type) {
+            Some(dir) => {
+                self.read_table(&dir, instrument, candle_type, date_from, date_to)
+                    .await
+            }
+            None => vec![],
+        }
+    }
+
+    async fn get_async(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        expiration_date: u64,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel> {
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        CandlesStorage::get_by_date_range(
+            self,
+            instrument,
+            side,
+            candle_type,
+            expiration_date,
+            now + 1,
+        )
+        .await
+    }
+
+    async fn get_instruments(&self) -> Vec<String> {
+        let mut result = HashSet::new();
+
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                if err.kind() != ErrorKind::NotFound {
+                    tracing::error!(
+                        "Can't read candles directory {:?}; Err: {:?}",
+                        self.root,
+                        err
+                    );
+                }
+                return vec![];
+            }
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let is_dir = entry.file_type().await.map(|file_type| file_type.is_dir());
+
+            if let (Ok(true), Some(name)) = (is_dir, entry.file_name().to_str()) {
+                result.insert(name.to_string());
+            }
+        }
+
+        result.into_iter().collect()
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        let dirs = match self.get_instrument_dir(instrument) {
+            Some(dir) => CandleType::ALL
+                .iter()
+                .map(|candle_type| dir.join((*candle_type as i32).to_string()))
+                .collect(),
+            None => return,
+        };
+
+        self.remove_dirs(instrument, dirs).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl SpreadsStorage for FileCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<SpreadCandleModel>,
+    ) {
+        if let Some(dir) = self.get_spread_table_dir(instrument, candle_type) {
+            self.save_table(&dir, instrument, candle_type, candles, None)
+                .await;
+        }
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        match self.get_spread_table_dir(instrument, candle_type) {
+            Some(dir) => {
+                self.read_table(&dir, instrument, candle_type, date_from, date_to)
+                    .await
+            }
+            None => vec![],
+        }
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        if let Some(dir) = self.get_instrument_dir(instrument) {
+            self.remove_dirs(instrument, vec![dir.join(SPREAD_DIR)])
+                .await;
+        }
+    }
+}
+
+impl FileCandlesStorage {
+    /// Returns false when the instruments file can't be written
+    async fn write_instruments(
+        &self,
+        path: &Path,
+        entities: &BTreeMap<String, InstrumentStorageEntity>,
+    ) -> bool {
+        let mut data = String::new();
+        for entity in entities.values() {
+            data.push_str(&serde_json::to_string(entity).unwrap());
+            data.push('\n');
+        }
+
+        let res = match fs::create_dir_all(&self.root).await {
+            Ok(_) => write_atomically(path, data.as_bytes()).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = &res {
+            tracing::error!(
+                "Error while persisting instruments to {:?}; Err: {:?}",
+                path,
+                err
+            );
+        }
+
+        res.is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl InstrumentMetadataStorage for FileCandlesStorage {
+    async fn save(&self, instruments: Vec<(String, InstrumentMetadata)>) -> Vec<String> {
+        let path = self.root.join(INSTRUMENTS_FILE);
+        let _write_lock = self.segments.lock().await;
+
+        let mut entities: BTreeMap<String, InstrumentStorageEntity> = read_instruments(&path)
+            .await
+            .into_iter()
+            .map(|entity| (entity.instrument.clone(), entity))
+            .collect();
+
+        let saved: Vec<String> = instruments
+            .iter()
+            .map(|(instrument, _)| instrument.clone())
+            .collect();
+
+        for (instrument, metadata) in instruments {
+            let entity = InstrumentStorageEntity::create(instrument.clone(), &metadata);
+            entities.insert(instrument, entity);
+        }
+
+        match self.write_instruments(&path, &entities).await {
+            true => vec![],
+            false => saved,
+        }
+    }
+
+    async fn delete(&self, instrument: &str) {
+        let path = self.root.join(INSTRUMENTS_FILE);
+        let _write_lock = self.segments.lock().await;
+
+        let mut entities: BTreeMap<String, InstrumentStorageEntity> = read_instruments(&path)
+            .await
+            .into_iter()
+            .map(|entity| (entity.instrument.clone(), entity))
+            .collect();
+
+        if entities.remove(instrument).is_some() {
+            self.write_instruments(&path, &entities).await;
+        }
+    }
+
+    async fn load(&self) -> Vec<(String, InstrumentMetadata)> {
+        read_instruments(&self.root.join(INSTRUMENTS_FILE))
+            .await
+            .into_iter()
+            .map(|entity| {
+                let metadata = entity.get_metadata();
+                (entity.instrument, metadata)
+            })
+            .collect()
+    }
+}
+
+async fn read_instruments(path: &Path) -> Vec<InstrumentStorageEntity> {
+    let data = match fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(err) => {
+            if err.kind() != ErrorKind::NotFound {
+                tracing::error!("Can't read instruments {:?}; Err: {:?}", path, err);
+            }
+            return vec![];
+        }
+    };
+
+    data.lines()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entity) => Some(entity),
+            Err(err) => {
+                tracing::warn!("Skipping invalid instrument in {:?}: {:?}", path, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Record of a segment file
+trait SegmentRecord: Sized + Send {
+    fn get_datetime(&self) -> u64;
+
+    fn to_record(&self, digits: Option<u32>) -> String;
+
+    fn parse_record(line: &str) -> Option<Self>;
+}
+
+/// What a save needs to know about a segment without reading it
+struct SegmentState {
+    dates: HashSet<u64>,
+    records: usize,
+    valid_len: u64,
+    len: u64,
+}
+
+impl SegmentState {
+    fn new<T>(segment: &Segment<T>) -> Self {
+        Self {
+            dates: segment.candles.keys().copied().collect(),
+            records: segment.records,
+            valid_len: segment.valid_len,
+            len: segment.len,
+        }
+    }
+}
+
+struct Segment<T> {
+    candles: BTreeMap<u64, T>,
+    records: usize,
+    /// Length up to the last complete record, a crash during a write leaves a torn tail behind it
+    valid_len: u64,
+    len: u64,
+}
+
+fn get_segment_path(dir: &Path, partition_key: &str) -> PathBuf {
+    dir.join(format!("{}.{}", partition_key, SEGMENT_EXTENSION))
+}
+
+async fn read_segment<T: SegmentRecord>(path: &Path) -> Segment<T> {
+    let mut result = Segment {
+        candles: BTreeMap::new(),
+        records: 0,
+        valid_len: 0,
+        len: 0,
+    };
+
+    let data = match fs::read(path).await {
+        Ok(data) => data,
+        Err(err) => {
+            if err.kind() != ErrorKind::NotFound {
+                tracing::error!("Can't read candle segment {:?}; Err: {:?}", path, err);
+            }
+            return result;
+        }
+    };
+
+    result.len = data.len() as u64;
+
+    // only newline terminated records are complete, anything after the last newline is a torn write
+    let mut start = 0;
+    while let Some(end) = data[start..].iter().position(|byte| *byte == b'\n') {
+        let line = &data[start..start + end];
+
+        match std::str::from_utf8(line).ok().and_then(T::parse_record) {
+            Some(candle) => {
+                result.candles.insert(candle.get_datetime(), candle);
+                result.records += 1;
+            }
+            None => tracing::warn!("Skipping invalid candle record in {:?} at {}", path, start),
+        }
+
+        start += end + 1;
+    }
+    result.valid_len = start as u64;
+
+    result
+}
+
+async fn append_segment(
+    path: &Path,
+    segment: &SegmentState,
+    records: &[u8],
+) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+
+    if segment.valid_len < segment.len {
+        tracing::warn!(
+            "Truncating the torn tail of {:?}; {} of {} bytes are valid",
+            path,
+            segment.valid_len,
+            segment.len
+        );
+        file.set_len(segment.valid_len).await?;
+    }
+
+    file.write_all(records).await?;
+    file.sync_data().await
+}
+
+/// Rewrites the segment with a single record per candle, returns its new length
+async fn compact_segment<T: SegmentRecord>(
+    path: &Path,
+    candles: &BTreeMap<u64, T>,
+) -> std::io::Result<u64> {
+    let mut data = String::new();
+    for candle in candles.values() {
+        data.push_str(&candle.to_record(None));
+        data.push('\n');
+    }
+
+    write_atomically(path, data.as_bytes()).await?;
+    Ok(data.len() as u64)
+}
+
+/// Writes a temporary file and renames it over the target
+async fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension(COMPACTION_EXTENSION);
+    let mut file = fs::File::create(&tmp_path).await?;
+    file.write_all(data).await?;
+    file.sync_all().await?;
+
+    fs::rename(&tmp_path, path).await?;
+
+    match path.parent() {
+        Some(dir) => sync_dir(dir).await,
+        None => Ok(()),
+    }
+}
+
+async fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    fs::File::open(dir).await?.sync_all().await
+}
+
+impl SegmentRecord for CandleModel {
+    fn get_datetime(&self) -> u64 {
+        self.datetime
+    }
+
+    fn to_record(&self, digits: Option<u32>) -> String {
+        format!(
+            "{};{};{};{};{};{};{};{};{};{}",
+            self.datetime,
+            format_price(self.open, digits),
+            format_price(self.close, digits),
+            format_price(self.high, digits),
+            format_price(self.low, digits),
+            self.ticks,
+            // empty for the candles without volume
+            self.volume
+                .map(|volume| volume.to_string())
+                .unwrap_or_default(),
+            format_price(self.vwap, digits),
+            self.open_time,
+            self.close_time,
+        )
+    }
+
+    fn parse_record(line: &str) -> Option<Self> {
+        let mut parts = line.split(';');
+
+        let candle = CandleModel {
+            datetime: parts.next()?.parse().ok()?,
+            open: parts.next()?.parse().ok()?,
+            close: parts.next()?.parse().ok()?,
+            high: parts.next()?.parse().ok()?,
+            low: parts.next()?.parse().ok()?,
+            ticks: parts.next()?.parse().ok()?,
+            volume: match parts.next()? {
+                "" => None,
+                volume => Some(volume.parse().ok()?),
+            },
+            vwap: parts.next()?.parse().ok()?,
+            open_time: parts.next()?.parse().ok()?,
+            close_time: parts.next()?.parse().ok()?,
+            synthetic: false,
+        };
+
+        match parts.next() {
+            Some(_) => None,
+            None => Some(candle),
+        }
+    }
+}
+
+impl SegmentRecord for SpreadCandleModel {
+    fn get_datetime(&self) -> u64 {
+        self.datetime
+    }
+
+    fn to_record(&self, _digits: Option<u32>) -> String {
+        format!(
+            "{};{};{};{};{};{}",
+            self.datetime, self.min, self.max, self.avg, self.close, self.ticks,
+        )
+    }
+
+    fn parse_record(line: &str) -> Option<Self> {
+        let mut parts = line.split(';');
+
+        let datetime = parts.next()?.parse().ok()?;
+        let candle = SpreadCandleModel {
+            datetime,
+            min: parts.next()?.parse().ok()?,
+            max: parts.next()?.parse().ok()?,
+            avg: parts.next()?.parse().ok()?,
+            close: parts.next()?.parse().ok()?,
+            ticks: parts.next()?.parse().ok()?,
+            close_time: datetime,
+        };
+
+        match parts.next() {
+            Some(_) => None,
+            None => Some(candle),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::{fs, io::AsyncWriteExt};
+
+    use crate::{
+        domain::{CandlesStorage, InstrumentMetadata, InstrumentMetadataStorage},
+        models::{CandleModel, CandleType, DayRollovers, PriceSide, SpreadCandleModel},
+    };
+
+    use super::{FileCandlesStorage, COMPACTION_MIN_RECORDS};
+
+    #[tokio::test]
+    async fn test_segments() {
+        let root = std::env::temp_dir().join(format!("candles-{}", uuid::Uuid::new_v4()));
+        let storage = FileCandlesStorage::new(&root, Arc::new(DayRollovers::default()));
+        let instrument = "EURUSD";
+
+        // 2022-09-07 13:49 and 2022-09-08 13:49 fall into different partitions
+        let first = CandleModel::new_from_rate(1662558540, 1662558540, 1.00012, Some(2.0));
+        let mut second = CandleModel::new_from_rate(1662644940, 1662644940, 1.5, None);
+        let save = |candles: Vec<CandleModel>| {
+            storage.bulk_save(
+                instrument,
+                PriceSide::Bid,
+                CandleType::Minute,
+                candles,
+                Some(4),
+            )
+        };
+
+        save(vec![first.clone(), second.clone()]).await;
+        second.close = 1.7;
+        save(vec![second.clone()]).await;
+
+        let candles = storage
+            .get_by_date_range(instrument, PriceSide::Bid, CandleType::Minute, 0, u64::MAX)
+            .await;
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].open, 1.0001);
+        assert_eq!(candles[0].volume, Some(2.0));
+        assert_eq!(candles[1].close, 1.7);
+        assert_eq!(candles[1].volume, None);
+
+        // a torn write of a crash is ignored and truncated on the first save after the restart
+        let segment = root.join("EURUSD/0/0/20220908.seg");
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&segment)
+            .await
+            .unwrap();
+        file.write_all(b"1662645000;1.8;1.").await.unwrap();
+        drop(file);
+
+        let storage = FileCandlesStorage::new(&root, Arc::new(DayRollovers::default()));
+        let save = |candles: Vec<CandleModel>| {
+            storage.bulk_save(
+                instrument,
+                PriceSide::Bid,
+                CandleType::Minute,
+                candles,
+                Some(4),
+            )
+        };
+
+        for _ in 0..COMPACTION_MIN_RECORDS {
+            save(vec![second.clone()]).await;
+        }
+        let data = fs::read_to_string(&segment).await.unwrap();
+        assert!(data.lines().count() < COMPACTION_MIN_RECORDS);
+        assert!(data.ends_with('\n'));
+
+        let candles = storage
+            .get_async(instrument, PriceSide::Bid, 1662600000, CandleType::Minute)
+            .await;
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].close, 1.7);
+
+        assert_eq!(
+            storage.get_instruments().await,
+            vec![instrument.to_string()]
+        );
+        storage.delete_tables(instrument).await;
+        assert!(storage.get_instruments().await.is_empty());
+
+        let _ = fs::remove_dir_all(&root).await;
+    }
+
+    #[tokio::test]
+    async fn test_spreads_and_instruments() {
+        use crate::domain::SpreadsStorage;
+
+        let root = std::env::temp_dir().join(format!("candles-{}", uuid::Uuid::new_v4()));
+        let storage = FileCandlesStorage::new(&root, Arc::new(DayRollovers::default()));
+        let instrument = "EURUSD";
+
+        let mut spread = SpreadCandleModel::new_from_spread(1662558540, 1662558540, 0.0002);
+        let save_spreads = |spreads: Vec<SpreadCandleModel>| {
+            SpreadsStorage::bulk_save(&storage, instrument, CandleType::Minute, spreads)
+        };
+        save_spreads(vec![spread.clone()]).await;
+        spread.update_by_spread(0.0004, 1662558541);
+        save_spreads(vec![spread]).await;
+
+        let candle = CandleModel::new_from_rate(1662558540, 1662558540, 1.1, None);
+        let candles = vec![candle];
+        CandlesStorage::bulk_save(
+            &storage,
+            instrument,
+            PriceSide::Bid,
+            CandleType::Minute,
+            candles,
+            None,
+        )
+        .await;
+        // the candle tables are dropped without the spread ones
+        CandlesStorage::delete_tables(&storage, instrument).await;
+
+        let spreads = SpreadsStorage::get_by_date_range(
+            &storage,
+            instrument,
+            CandleType::Minute,
+            0,
+            u64::MAX,
+        )
+        .await;
+        assert_eq!(spreads.len(), 1);
+        assert_eq!(spreads[0].max, 0.0004);
+        assert_eq!(spreads[0].ticks, 2);
+
+        let metadata = InstrumentMetadata {
+            first_seen: 1662558540,
+            retired: true,
+            ..Default::default()
+        };
+        let saved = vec![(instrument.to_string(), metadata)];
+        assert!(storage.save(saved).await.is_empty());
+        let saved = vec![("GBPUSD".to_string(), InstrumentMetadata::default())];
+        assert!(storage.save(saved).await.is_empty());
+
+        let mut instruments = storage.load().await;
+        instruments.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(instruments.len(), 2);
+        assert_eq!(instruments[0].0, "EURUSD");
+        assert_eq!(instruments[0].1.first_seen, 1662558540);
+        assert!(instruments[0].1.retired);
+
+        storage.delete(instrument).await;
+        let instruments = storage.load().await;
+        assert_eq!(instruments.len(), 1);
+        assert_eq!(instruments[0].0, "GBPUSD");
+
+        SpreadsStorage::delete_tables(&storage, instrument).await;
+        assert!(storage.get_instruments().await.is_empty());
+
+        let _ = fs::remove_dir_all(&root).await;
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use tokio::sync::RwLock;
+
+use crate::models::{CandleModel, CandleType, PriceSide, SpreadCandleModel};
+
+use super::{CandlesStorage, InstrumentMetadata, InstrumentMetadataStorage, SpreadsStorage};
+
+type TableKey = (String, PriceSide, CandleType);
+
+/// Candles, spread candles and instrument metadata kept in the process memory,
+/// the digits are not applied
+#[derive(Default)]
+pub struct InMemoryCandlesStorage {
+    tables: RwLock<HashMap<TableKey, BTreeMap<u64, CandleModel>>>,
+    spread_tables: RwLock<HashMap<(String, CandleType), BTreeMap<u64, SpreadCandleModel>>>,
+    instruments: RwLock<HashMap<String, InstrumentMetadata>>,
+}
+
+impl InMemoryCandlesStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl CandlesStorage for InMemoryCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        candles: Vec<CandleModel>,
+        _digits: Option<u32>,
+    ) {
+        let mut tables = self.tables.write().await;
+        let table = tables
+            .entry((instrument.to_string(), side, candle_type))
+            .or_default();
+
+        for candle in candles {
+            table.insert(candle.datetime, candle);
+        }
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<CandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        let tables = self.tables.read().await;
+
+        tables
+            .get(&(instrument.to_string(), side, candle_type))
+            .map(|table| {
+                table
+                    .range(date_from..date_to)
+                    .map(|(_, candle)| candle.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn get_async(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        expiration_date: u64,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel> {
+        CandlesStorage::get_by_date_range(
+            self,
+            instrument,
+            side,
+            candle_type,
+            expiration_date,
+            u64::MAX,
+        )
+        .await
+    }
+
+    async fn get_instruments(&self) -> Vec<String> {
+        let tables = self.tables.read().await;
+        let instruments: HashSet<&String> =
+            tables.keys().map(|(instrument, _, _)| instrument).collect();
+
+        instruments.into_iter().cloned().collect()
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        self.tables
+            .write()
+            .await
+            .retain(|(table_instrument, _, _), _| table_instrument != instrument);
+    }
+}
+
+#[async_trait::async_trait]
+impl SpreadsStorage for InMemoryCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<SpreadCandleModel>,
+    ) {
+        let mut spread_tables = self.spread_tables.write().await;
+        let table = spread_tables
+            .entry((instrument.to_string(), candle_type))
+            .or_default();
+
+        for candle in candles {
+            table.insert(candle.datetime, candle);
+        }
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        let spread_tables = self.spread_tables.read().await;
+
+        spread_tables
+            .get(&(instrument.to_string(), candle_type))
+            .map(|table| {
+                table
+                    .range(date_from..date_to)
+                    .map(|(_, candle)| candle.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        self.spread_tables
+            .write()
+            .await
+            .retain(|(table_instrument, _), _| table_instrument != instrument);
+    }
+}
+
+#[async_trait::async_trait]
+impl InstrumentMetadataStorage for InMemoryCandlesStorage {
+    async fn save(&self, instruments: Vec<(String, InstrumentMetadata)>) -> Vec<String> {
+        self.instruments.write().await.extend(instruments);
+        vec![]
+    }
+
+    async fn delete(&self, instrument: &str) {
+        self.instruments.write().await.remove(instrument);
+    }
+
+    async fn load(&self) -> Vec<(String, InstrumentMetadata)> {
+        self.instruments
+            .read()
+            .await
+            .iter()
+            .map(|(instrument, metadata)| (instrument.clone(), metadata.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, sync::Arc};
+
+use my_no_sql_tcp_reader::MyNoSqlDataReader;
+use tokio::sync::RwLock;
+
+use crate::no_sql::spot_instrument::SpotInstrumentNoSqlEntity;
+
+use super::{InstrumentAssets, InstrumentStorage};
+
+/// Local copy of the spot-instruments MyNoSql table. Only the ticks of its enabled
+/// instruments are aggregated, their digits and assets enrich the instrument storage.
+/// Aliases route the names of the liquidity providers to the canonical instruments.
+pub struct InstrumentDictionary {
+    reader: Arc<MyNoSqlDataReader<SpotInstrumentNoSqlEntity>>,
+    instrument_storage: Arc<InstrumentStorage>,
+    enabled: RwLock<HashMap<String, Arc<SpotInstrumentNoSqlEntity>>>,
+    canonical_by_alias: HashMap<String, String>,
+}
+
+impl InstrumentDictionary {
+    pub fn new(
+        reader: Arc<MyNoSqlDataReader<SpotInstrumentNoSqlEntity>>,
+        instrument_storage: Arc<InstrumentStorage>,
+        canonical_by_alias: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            reader,
+            instrument_storage,
+            enabled: RwLock::new(HashMap::new()),
+            canonical_by_alias,
+        }
+    }
+
+    /// Canonical name of the instrument, the name itself when it is not an alias
+    pub fn get_canonical<'a>(&'a self, instrument: &'a str) -> &'a str {
+        self.canonical_by_alias
+            .get(instrument)
+            .map_or(instrument, |canonical| canonical.as_str())
+    }
+
+    /// Reloads the instruments from the reader, false while the table is not received yet
+    pub async fn refresh(&self) -> bool {
+        let entities = match self.reader.get_table_snapshot_as_vec().await {
+            Some(entities) => entities,
+            None => return false,
+        };
+
+        let enabled = get_enabled(entities);
+
+        for (instrument, entity) in enabled.iter() {
+            self.instrument_storage
+                .set_digits(instrument, entity.accuracy)
+                .await;
+            self.instrument_storage
+                .set_assets(
+                    instrument,
+                    InstrumentAssets {
+                        base: entity.base_asset.clone(),
+                        quote: entity.quote_asset.clone(),
+                    },
+                )
+                .await;
+        }
+
+        tracing::info!(
+            "Instrument dictionary refreshed; enabled: {}",
+            enabled.len()
+        );
+        *self.enabled.write().await = enabled;
+
+        true
+    }
+
+    /// Retired instruments are not enabled even when the dictionary lists them
+    pub async fn is_enabled(&self, instrument: &str) -> bool {
+        self.enabled.read().await.contains_key(instrument)
+            && !self.instrument_storage.is_retired(instrument).await
+    }
+}
+
+fn get_enabled(
+    entities: Vec<Arc<SpotInstrumentNoSqlEntity>>,
+) -> HashMap<String, Arc<SpotInstrumentNoSqlEntity>> {
+    entities
+        .into_iter()
+        .filter(|entity| entity.is_enabled)
+        .map(|entity| (entity.row_key.clone(), entity))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::get_enabled;
+    use crate::no_sql::spot_instrument::SpotInstrumentNoSqlEntity;
+
+    #[test]
+    fn test_only_enabled_instruments() {
+        let entities: Vec<Arc<SpotInstrumentNoSqlEntity>> = serde_json::from_str::<
+            Vec<SpotInstrumentNoSqlEntity>,
+        >(
+            r#"[
+                {"PartitionKey":"spot","RowKey":"BTCUSD","BaseAsset":"BTC","QuoteAsset":"USD","Accuracy":2,"IsEnabled":true},
+                {"PartitionKey":"spot","RowKey":"ETHUSD","BaseAsset":"ETH","QuoteAsset":"USD","Accuracy":2,"IsEnabled":false},
+                {"PartitionKey":"spot","RowKey":"XRPUSD","Accuracy":5}
+            ]"#,
+        )
+        .unwrap()
+        .into_iter()
+        .map(Arc::new)
+        .collect();
+
+        let enabled = get_enabled(entities);
+
+        assert_eq!(enabled.len(), 1);
+        assert_eq!(enabled["BTCUSD"].base_asset, "BTC");
+        assert_eq!(enabled["BTCUSD"].accuracy, 2);
+    }
+}
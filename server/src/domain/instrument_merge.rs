@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    app::AppContext,
+    models::{CandleModel, PriceSide, SpreadCandleModel, Timeframe},
+};
+
+/// Merges the candle and spread history of `alias` into `canonical`, candles of the same period
+/// are combined. The alias is retired afterwards, so its history is added only once.
+/// Returns the number of merged candles, None when the alias or the canonical instrument is retired.
+pub async fn merge_instrument_history(
+    context: &Arc<AppContext>,
+    alias: &str,
+    canonical: &str,
+) -> Option<usize> {
+    let _history_guard = context.history_lock.lock().await;
+
+    if context.instrument_storage.is_retired(alias).await
+        || !context.instrument_storage.is_active(canonical).await
+    {
+        return None;
+    }
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let digits = context.instrument_storage.get_digits(canonical).await;
+    let mut merged_count = 0;
+
+    for side in PriceSide::ALL {
+        for timeframe in context.settings.inner.get_timeframes().iter() {
+            let candle_type = timeframe.candle_type;
+
+            // the cache holds the most recent state of the candles that are not persisted yet
+            let mut alias_candles: BTreeMap<u64, CandleModel> = BTreeMap::new();
+            if timeframe.persist {
+                let stored = context
+                    .candles_storage
+                    .get_by_date_range(alias, side, candle_type, 0, now + 1)
+                    .await;
+                alias_candles.extend(stored.into_iter().map(|candle| (candle.datetime, candle)));
+            }
+            let cached = context
+                .cache
+                .get_by_date_range(alias.to_string(), candle_type, side, 0, u64::MAX)
+                .await;
+            alias_candles.extend(cached.into_iter().map(|candle| (candle.datetime, candle)));
+
+            let (date_from, date_to) = match (
+                alias_candles.keys().next(),
+                alias_candles.keys().next_back(),
+            ) {
+                (Some(first), Some(last)) => (*first, *last + 1),
+                _ => continue,
+            };
+
+            let alias_candles: Vec<CandleModel> = alias_candles.into_values().collect();
+            merged_count += alias_candles.len();
+
+            context
+                .cache
+                .merge(canonical, candle_type, side, &alias_candles)
+                .await;
+
+            if !timeframe.persist {
+                continue;
+            }
+
+            let canonical_candles: BTreeMap<u64, CandleModel> = context
+                .candles_storage
+                .get_by_date_range(canonical, side, candle_type, date_from, date_to)
+                .await
+                .into_iter()
+                .map(|candle| (candle.datetime, candle))
+                .collect();
+
+            let merged = alias_candles
+                .iter()
+                .map(|candle| match canonical_candles.get(&candle.datetime) {
+                    Some(canonical_candle) => canonical_candle.merge(candle),
+                    None => candle.clone(),
+                })
+                .collect();
+
+            context
+                .candles_storage
+                .bulk_save(canonical, side, candle_type, merged, digits)
+                .await;
+        }
+    }
+
+    let mut merged_spreads_count = 0;
+    for timeframe in context.settings.inner.get_timeframes().iter() {
+        merged_spreads_count += merge_spreads(context, alias, canonical, timeframe, now).await;
+    }
+
+    context.instrument_storage.merge(alias, canonical).await;
+    context.instrument_storage.retire(alias).await;
+    context.cache.remove(alias).await;
+    context.spreads_cache.remove(alias).await;
+
+    tracing::info!(
+        "Merged {} candles and {} spread candles of {} into {}",
+        merged_count,
+        merged_spreads_count,
+        alias,
+        canonical
+    );
+
+    Some(merged_count)
+}
+
+/// Returns the number of merged spread candles of the timeframe
+async fn merge_spreads(
+    context: &Arc<AppContext>,
+    alias: &str,
+    canonical: &str,
+    timeframe: &Timeframe,
+    now: u64,
+) -> usize {
+    let candle_type = timeframe.candle_type;
+
+    let mut alias_spreads: BTreeMap<u64, SpreadCandleModel> = BTreeMap::new();
+    if timeframe.persist {
+        let stored = context
+            .spreads_storage
+            .get_by_date_range(alias, candle_type, 0, now + 1)
+            .await;
+        alias_spreads.extend(stored.into_iter().map(|candle| (candle.datetime, candle)));
+    }
+    let cached = context
+        .spreads_cache
+        .get_by_date_range(alias, candle_type, 0, u64::MAX)
+        .await;
+    alias_spreads.extend(cached.into_iter().map(|candle| (candle.datetime, candle)));
+
+    let (date_from, date_to) = match (
+        alias_spreads.keys().next(),
+        alias_spreads.keys().next_back(),
+    ) {
+        (Some(first), Some(last)) => (*first, *last + 1),
+        _ => return 0,
+    };
+
+    let alias_spreads: Vec<SpreadCandleModel> = alias_spreads.into_values().collect();
+
+    context
+        .spreads_cache
+        .merge(canonical, candle_type, &alias_spreads)
+        .await;
+
+    if timeframe.persist {
+        let canonical_spreads: BTreeMap<u64, SpreadCandleModel> = context
+            .spreads_storage
+            .get_by_date_range(canonical, candle_type, date_from, date_to)
+            .await
+            .into_iter()
+            .map(|candle| (candle.datetime, candle))
+            .collect();
+
+        let merged = alias_spreads
+            .iter()
+            .map(|candle| match canonical_spreads.get(&candle.datetime) {
+                Some(canonical_candle) => canonical_candle.merge(candle),
+                None => candle.clone(),
+            })
+            .collect();
+
+        context
+            .spreads_storage
+            .bulk_save(canonical, candle_type, merged)
+            .await;
+    }
+
+    alias_spreads.len()
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use crate::app::AppContext;
+
+/// Marks the instrument retired and drops it from the caches, its ticks are rejected from now on
+/// and the read APIs no longer list it. With `purge` its candle and spread tables and its instrument
+/// storage row are deleted as well, so it is registered again only while the instrument dictionary
+/// still enables it.
+/// Returns false for an unknown instrument.
+pub async fn retire_instrument(context: &Arc<AppContext>, instrument: &str, purge: bool) -> bool {
+    // a persistence cycle in progress would recreate the purged tables
+    let _history_guard = context.history_lock.lock().await;
+
+    if !context.instrument_storage.retire(instrument).await {
+        return false;
+    }
+
+    context.cache.remove(instrument).await;
+    context.spreads_cache.remove(instrument).await;
+
+    if purge {
+        context.candles_storage.delete_tables(instrument).await;
+        context.spreads_storage.delete_tables(instrument).await;
+        context.instrument_storage.delete(instrument).await;
+    }
+
+    tracing::info!("Retired instrument {}; purged: {}", instrument, purge);
+
+    true
+}
@@ -1,121 +1,535 @@
-use std::{collections::HashSet, sync::{Arc, atomic::AtomicBool}};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::AtomicBool, Arc},
+};
 
-use futures::stream::StreamExt;
 use azure_core::Pageable;
-use azure_data_tables::{prelude::{TableClient, TableServiceClient}, operations::QueryEntityResponse};
+use azure_data_tables::{
+    operations::QueryEntityResponse,
+    prelude::{TableClient, TableServiceClient},
+};
+use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, RwLock};
 
+use crate::models::CandleType;
+
 pub static TABLE_NAME: &str = "instrumentstorage";
 pub static PARTITION_KEY: &str = "INSTRUMENTSTORAGE";
 
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentMetadata {
+    /// 0 for instruments registered before the metadata was tracked
+    pub first_seen: u64,
+    pub last_tick: u64,
+    pub tick_count: u64,
+    pub candle_types: HashSet<CandleType>,
+    /// Retired instruments are neither restored nor persisted
+    pub retired: bool,
+}
+
+/// Assets of an instrument as listed in the instrument dictionary
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstrumentAssets {
+    pub base: String,
+    pub quote: String,
+}
+
+/// Persistent storage of the instrument metadata, provided by the selected candles storage backend
+#[async_trait::async_trait]
+pub trait InstrumentMetadataStorage: Send + Sync {
+    /// Inserts or replaces the metadata, returns the instruments that are not saved
+    async fn save(&self, instruments: Vec<(String, InstrumentMetadata)>) -> Vec<String>;
+
+    async fn load(&self) -> Vec<(String, InstrumentMetadata)>;
+
+    /// Removes the persisted metadata of the instrument
+    async fn delete(&self, instrument: &str);
+}
+
 pub struct InstrumentStorage {
-    pub instruments: RwLock<HashSet<String>>,
-    pub persist_table_client: Arc<TableClient>,
-    is_table_created: AtomicBool,
-    persist_queue: Mutex<Vec<String>>,
+    pub instruments: RwLock<HashMap<String, InstrumentMetadata>>,
+    /// Price precision of the instruments, not persisted as it comes from the configuration
+    digits: RwLock<HashMap<String, u32>>,
+    assets: RwLock<HashMap<String, InstrumentAssets>>,
+    metadata_storage: Arc<dyn InstrumentMetadataStorage>,
+    persist_queue: Mutex<HashSet<String>>,
 }
 
+/// Numbers are kept as strings: Azure Tables stores untyped JSON numbers as Int32 or Double
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstrumentStorageEntity {
     #[serde(rename = "PartitionKey")]
     pub partition_key: String,
     #[serde(rename = "RowKey")]
     pub instrument: String,
+    #[serde(rename = "FirstSeen", default)]
+    pub first_seen: String,
+    #[serde(rename = "LastTick", default)]
+    pub last_tick: String,
+    #[serde(rename = "TickCount", default)]
+    pub tick_count: String,
+    #[serde(rename = "CandleTypes", default)]
+    pub candle_types: String,
+    #[serde(rename = "Retired", default)]
+    pub retired: bool,
+}
+
+impl InstrumentStorageEntity {
+    pub fn create(instrument: String, metadata: &InstrumentMetadata) -> Self {
+        Self {
+            partition_key: PARTITION_KEY.to_string(),
+            instrument,
+            first_seen: metadata.first_seen.to_string(),
+            last_tick: metadata.last_tick.to_string(),
+            tick_count: metadata.tick_count.to_string(),
+            candle_types: format_candle_types(&metadata.candle_types),
+            retired: metadata.retired,
+        }
+    }
+
+    pub fn get_metadata(&self) -> InstrumentMetadata {
+        InstrumentMetadata {
+            first_seen: self.first_seen.parse().unwrap_or(0),
+            last_tick: self.last_tick.parse().unwrap_or(0),
+            tick_count: self.tick_count.parse().unwrap_or(0),
+            candle_types: parse_candle_types(&self.candle_types),
+            retired: self.retired,
+        }
+    }
+}
+
+/// Sorted comma separated candle type numbers
+pub fn format_candle_types(candle_types: &HashSet<CandleType>) -> String {
+    let mut candle_types: Vec<i32> = candle_types
+        .iter()
+        .map(|candle_type| *candle_type as i32)
+        .collect();
+    candle_types.sort();
+
+    candle_types
+        .iter()
+        .map(|candle_type| candle_type.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn parse_candle_types(candle_types: &str) -> HashSet<CandleType> {
+    candle_types
+        .split(',')
+        .filter_map(|candle_type| candle_type.parse::<i32>().ok())
+        .filter_map(|candle_type| CandleType::try_from(candle_type).ok())
+        .collect()
 }
 
 impl InstrumentStorage {
-    pub fn new(table_service_client: Arc<TableServiceClient>) -> Self {
+    pub fn new(
+        metadata_storage: Arc<dyn InstrumentMetadataStorage>,
+        digits: HashMap<String, u32>,
+    ) -> Self {
         Self {
-            instruments: RwLock::new(HashSet::new()),
-            persist_table_client: Arc::new(table_service_client.table_client(TABLE_NAME)),
-            is_table_created: AtomicBool::new(false),
-            persist_queue: Mutex::new(Vec::with_capacity(100)),
+            instruments: RwLock::new(HashMap::new()),
+            digits: RwLock::new(digits),
+            assets: RwLock::new(HashMap::new()),
+            metadata_storage,
+            persist_queue: Mutex::new(HashSet::with_capacity(100)),
         }
     }
 
-    pub async fn add(&self, instrument: String) {
-        let mut set = self.instruments.write().await;
-        if set.contains(&instrument) {
-            return;
+    pub async fn record_tick(&self, instrument: &str, date: u64) {
+        {
+            let mut instruments = self.instruments.write().await;
+            match instruments.get_mut(instrument) {
+                Some(metadata) => {
+                    metadata.last_tick = u64::max(metadata.last_tick, date);
+                    metadata.tick_count += 1;
+                }
+                None => {
+                    instruments.insert(
+                        instrument.to_string(),
+                        InstrumentMetadata {
+                            first_seen: date,
+                            last_tick: date,
+                            tick_count: 1,
+                            candle_types: HashSet::new(),
+                            retired: false,
+                        },
+                    );
+                }
+            }
         }
 
-        set.insert(instrument.clone());
-        self.persist_queue.lock().await.push(instrument);
+        self.enqueue(instrument).await;
+    }
+
+    /// Registers the instrument of a trade, trades are not quotes and leave the tick count as is
+    pub async fn record_trade(&self, instrument: &str, date: u64) {
+        {
+            let mut instruments = self.instruments.write().await;
+            if instruments.contains_key(instrument) {
+                return;
+            }
+
+            instruments.insert(
+                instrument.to_string(),
+                InstrumentMetadata {
+                    first_seen: date,
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.enqueue(instrument).await;
+    }
+
+    pub async fn add_candle_type(&self, instrument: &str, candle_type: CandleType) {
+        {
+            let mut instruments = self.instruments.write().await;
+            match instruments.get_mut(instrument) {
+                Some(metadata) => {
+                    if !metadata.candle_types.insert(candle_type) {
+                        return;
+                    }
+                }
+                None => return,
+            }
+        }
+
+        self.enqueue(instrument).await;
+    }
+
+    /// Returns false for an unknown instrument
+    pub async fn retire(&self, instrument: &str) -> bool {
+        {
+            let mut instruments = self.instruments.write().await;
+            match instruments.get_mut(instrument) {
+                Some(metadata) => metadata.retired = true,
+                None => return false,
+            }
+        }
+
+        self.enqueue(instrument).await;
+        true
+    }
+
+    /// Removes the instrument and its persisted row, it is registered again by its next tick
+    pub async fn delete(&self, instrument: &str) {
+        self.instruments.write().await.remove(instrument);
+        self.persist_queue.lock().await.remove(instrument);
+        self.metadata_storage.delete(instrument).await;
+    }
+
+    pub async fn is_retired(&self, instrument: &str) -> bool {
+        self.instruments
+            .read()
+            .await
+            .get(instrument)
+            .is_some_and(|metadata| metadata.retired)
+    }
+
+    pub async fn get_retired(&self) -> HashSet<String> {
+        self.instruments
+            .read()
+            .await
+            .iter()
+            .filter(|(_, metadata)| metadata.retired)
+            .map(|(instrument, _)| instrument.clone())
+            .collect()
+    }
+
+    /// Adds the metadata of the alias to the canonical instrument
+    pub async fn merge(&self, alias: &str, canonical: &str) {
+        {
+            let mut instruments = self.instruments.write().await;
+            let alias_metadata = match instruments.get(alias) {
+                Some(metadata) => metadata.clone(),
+                None => return,
+            };
+
+            let metadata = instruments.entry(canonical.to_string()).or_default();
+            metadata.first_seen = match (metadata.first_seen, alias_metadata.first_seen) {
+                (0, first_seen) | (first_seen, 0) => first_seen,
+                (first_seen, alias_first_seen) => u64::min(first_seen, alias_first_seen),
+            };
+            metadata.last_tick = u64::max(metadata.last_tick, alias_metadata.last_tick);
+            metadata.tick_count += alias_metadata.tick_count;
+            metadata.candle_types.extend(alias_metadata.candle_types);
+        }
+
+        self.enqueue(canonical).await;
+    }
+
+    pub async fn set_digits(&self, instrument: &str, digits: u32) {
+        self.digits
+            .write()
+            .await
+            .insert(instrument.to_string(), digits);
+    }
+
+    pub async fn get_digits(&self, instrument: &str) -> Option<u32> {
+        self.digits.read().await.get(instrument).copied()
+    }
+
+    pub async fn set_assets(&self, instrument: &str, assets: InstrumentAssets) {
+        self.assets
+            .write()
+            .await
+            .insert(instrument.to_string(), assets);
+    }
+
+    pub async fn get_assets(&self, instrument: &str) -> Option<InstrumentAssets> {
+        self.assets.read().await.get(instrument).cloned()
+    }
+
+    async fn enqueue(&self, instrument: &str) {
+        let mut queue = self.persist_queue.lock().await;
+        if !queue.contains(instrument) {
+            queue.insert(instrument.to_string());
+        }
     }
 
     pub async fn contains(&self, instrument: &str) -> bool {
-        self.instruments.read().await.contains(instrument)
+        self.instruments.read().await.contains_key(instrument)
+    }
+
+    /// Known and not retired, the read APIs serve only such instruments
+    pub async fn is_active(&self, instrument: &str) -> bool {
+        self.instruments
+            .read()
+            .await
+            .get(instrument)
+            .map_or(false, |metadata| !metadata.retired)
+    }
+
+    pub async fn get_instruments(&self) -> Vec<String> {
+        self.instruments.read().await.keys().cloned().collect()
+    }
+
+    pub async fn get_active_instruments(&self) -> Vec<String> {
+        self.instruments
+            .read()
+            .await
+            .iter()
+            .filter(|(_, metadata)| !metadata.retired)
+            .map(|(instrument, _)| instrument.clone())
+            .collect()
+    }
+
+    pub async fn get_all(&self) -> Vec<(String, InstrumentMetadata)> {
+        self.instruments
+            .read()
+            .await
+            .iter()
+            .map(|(instrument, metadata)| (instrument.clone(), metadata.clone()))
+            .collect()
     }
 
     pub async fn persist(&self) {
-        let table_client = self.persist_table_client.clone();
+        let to_persist: Vec<String> = self.persist_queue.lock().await.drain().collect();
 
-        if !self.is_table_created.load(std::sync::atomic::Ordering::Acquire) {
-            let _ = table_client.create().await;
-            self.is_table_created.store(true, std::sync::atomic::Ordering::Release);
+        if to_persist.is_empty() {
+            return;
         }
 
-        let instruments = &mut self.persist_queue.lock().await;
+        let to_persist: Vec<(String, InstrumentMetadata)> = {
+            let instruments = self.instruments.read().await;
+            to_persist
+                .into_iter()
+                .filter_map(|instrument| {
+                    let metadata = instruments.get(&instrument)?.clone();
+                    Some((instrument, metadata))
+                })
+                .collect()
+        };
 
-        if instruments.is_empty() {
-            return;
+        for instrument in self.metadata_storage.save(to_persist).await {
+            // retry on the next cycle
+            self.enqueue(&instrument).await;
         }
+    }
+
+    pub async fn restore(&self) {
+        tracing::info!("Restoring instrument's storage...");
+
+        let restored = self.metadata_storage.load().await;
+        let count = restored.len();
+        self.instruments.write().await.extend(restored);
+
+        tracing::info!("Restored instrument's storage; count: {}", count);
+    }
+}
+
+/// Instrument metadata in the `instrumentstorage` table of the Azure account
+pub struct AzureInstrumentMetadataStorage {
+    table_client: Arc<TableClient>,
+    is_table_created: AtomicBool,
+}
+
+impl AzureInstrumentMetadataStorage {
+    pub fn new(table_service_client: Arc<TableServiceClient>) -> Self {
+        Self {
+            table_client: Arc::new(table_service_client.table_client(TABLE_NAME)),
+            is_table_created: AtomicBool::new(false),
+        }
+    }
+
+    async fn create_table(&self) {
+        if !self
+            .is_table_created
+            .load(std::sync::atomic::Ordering::Acquire)
+        {
+            let _ = self.table_client.create().await;
+            self.is_table_created
+                .store(true, std::sync::atomic::Ordering::Release);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl InstrumentMetadataStorage for AzureInstrumentMetadataStorage {
+    async fn save(&self, instruments: Vec<(String, InstrumentMetadata)>) -> Vec<String> {
+        self.create_table().await;
+
+        let mut failed = Vec::new();
 
-        while let Some(instrument) = instruments.pop() {
-            let entity_client = table_client
+        for (instrument, metadata) in instruments {
+            let entity_client = self
+                .table_client
                 .partition_key_client(PARTITION_KEY)
                 .entity_client(&instrument)
                 .unwrap();
 
-            let entity = InstrumentStorageEntity {
-                instrument: instrument,
-                partition_key: PARTITION_KEY.to_string(),
-            };
+            let entity = InstrumentStorageEntity::create(instrument.clone(), &metadata);
 
             let res = entity_client.insert_or_replace(entity).unwrap().await;
 
-            match res {
-                Ok(_) => {}
-                Err(err) => {
-                    tracing::error!("Error while persisting instrument: {:?};", err)
-                }
+            if let Err(err) = res {
+                tracing::error!("Error while persisting instrument: {:?};", err);
+                failed.push(instrument);
             }
         }
-    }
 
-    //returns latest timestamp
-    pub async fn restore(&self) {
-        let table_client = self.persist_table_client.clone();
-        
-        if !self.is_table_created.load(std::sync::atomic::Ordering::Acquire) {
-            let _ = table_client.create().await;
-            self.is_table_created.store(true, std::sync::atomic::Ordering::Release);
-        }
+        failed
+    }
 
-        tracing::info!("Restoring instrument's storage...");
+    async fn load(&self) -> Vec<(String, InstrumentMetadata)> {
+        self.create_table().await;
 
-        let mut count = 0;
-        let mut stream: Pageable<QueryEntityResponse<InstrumentStorageEntity>, _> = table_client
+        let mut result = Vec::new();
+        let mut stream: Pageable<QueryEntityResponse<InstrumentStorageEntity>, _> = self
+            .table_client
             .query()
             .initial_partition_key(PARTITION_KEY)
             .into_stream();
 
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(entity) => {
-                        let mut set = self.instruments.write().await;
-                        for entity in entity.entities {
-                            count += 1;
-                            set.insert(entity.instrument);
-                        }
-                    }
-                    Err(err) => {
-                        tracing::error!("Error while restoring instrument's storage: {:?};", err)
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(entity) => {
+                    for entity in entity.entities {
+                        let metadata = entity.get_metadata();
+                        result.push((entity.instrument, metadata));
                     }
                 }
+                Err(err) => {
+                    tracing::error!("Error while restoring instrument's storage: {:?};", err)
+                }
             }
+        }
+
+        result
+    }
+
+    async fn delete(&self, instrument: &str) {
+        self.create_table().await;
+
+        let entity_client = self
+            .table_client
+            .partition_key_client(PARTITION_KEY)
+            .entity_client(instrument)
+            .unwrap();
+
+        if let Err(err) = entity_client.delete().await {
+            tracing::error!("Error while deleting instrument {}: {:?};", instrument, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    };
+
+    use super::{
+        InstrumentMetadata, InstrumentMetadataStorage, InstrumentStorage, InstrumentStorageEntity,
+    };
+    use crate::{domain::InMemoryCandlesStorage, models::CandleType};
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let metadata = InstrumentMetadata {
+            first_seen: 1662559404,
+            last_tick: 1662559474,
+            tick_count: 42,
+            candle_types: HashSet::from([CandleType::Day, CandleType::Minute]),
+            retired: true,
+        };
+
+        let entity = InstrumentStorageEntity::create("EURUSD".to_string(), &metadata);
+        assert_eq!(entity.candle_types, "0,2");
+
+        let restored = entity.get_metadata();
+        assert_eq!(restored.first_seen, 1662559404);
+        assert_eq!(restored.last_tick, 1662559474);
+        assert_eq!(restored.tick_count, 42);
+        assert_eq!(restored.candle_types, metadata.candle_types);
+        assert!(restored.retired);
+    }
+
+    #[test]
+    fn test_metadata_of_legacy_entity() {
+        let entity: InstrumentStorageEntity =
+            serde_json::from_str(r#"{"PartitionKey":"INSTRUMENTSTORAGE","RowKey":"EURUSD"}"#)
+                .unwrap();
+
+        let metadata = entity.get_metadata();
+        assert_eq!(metadata.first_seen, 0);
+        assert_eq!(metadata.tick_count, 0);
+        assert!(metadata.candle_types.is_empty());
+        assert!(!metadata.retired);
+    }
+
+    #[tokio::test]
+    async fn test_retired_instrument_is_not_active() {
+        let storage =
+            InstrumentStorage::new(Arc::new(InMemoryCandlesStorage::new()), HashMap::new());
+        storage.record_tick("EURUSD", 1662559404).await;
+        storage.record_tick("BTCUSD", 1662559404).await;
+        assert!(storage.is_active("EURUSD").await);
+
+        assert!(storage.retire("EURUSD").await);
+        assert!(storage.contains("EURUSD").await);
+        assert!(!storage.is_active("EURUSD").await);
+        assert!(!storage.is_active("ETHUSD").await);
+        assert_eq!(
+            storage.get_active_instruments().await,
+            vec!["BTCUSD".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_instrument_is_not_persisted() {
+        let metadata_storage = Arc::new(InMemoryCandlesStorage::new());
+        let storage = InstrumentStorage::new(metadata_storage.clone(), HashMap::new());
+        storage.record_tick("TESTUSD", 1662559404).await;
+        storage.persist().await;
+
+        assert!(storage.retire("TESTUSD").await);
+        storage.delete("TESTUSD").await;
+        storage.persist().await;
 
-            tracing::info!("Restored instrument's storage; count: {}", count);
+        assert!(!storage.contains("TESTUSD").await);
+        assert!(metadata_storage.load().await.is_empty());
     }
 }
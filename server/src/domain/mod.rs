@@ -1,15 +1,45 @@
 mod database;
-mod request_counter;
+mod candles_storage;
+mod in_memory_candles_storage;
+mod file_candles_storage;
+mod sqlite_candles_storage;
 mod instrument_storage;
+mod instrument_dictionary;
+mod instrument_merge;
+mod instrument_retirement;
 mod azure_table_name_generators;
+mod candles_history;
+mod spread_storage;
+mod tick_validation;
 
-pub use database::Database;
-pub use request_counter::DatabaseImpl;
-pub use request_counter::RequestCounter;
 pub use instrument_storage::InstrumentStorage;
+pub use instrument_storage::InstrumentMetadata;
+pub use instrument_storage::InstrumentAssets;
+pub use instrument_storage::InstrumentMetadataStorage;
+pub use instrument_storage::AzureInstrumentMetadataStorage;
+
+pub use instrument_dictionary::InstrumentDictionary;
+pub use instrument_merge::merge_instrument_history;
+pub use instrument_retirement::retire_instrument;
 
 pub use database::persist_candles;
 pub use database::restore_candles;
 pub use database::CandlesPersistentAzureStorage;
 
-pub use azure_table_name_generators::*;
\ No newline at end of file
+pub use candles_storage::CandlesStorage;
+pub use candles_storage::CandlesStorageType;
+pub use candles_storage::SpreadsStorage;
+pub use in_memory_candles_storage::InMemoryCandlesStorage;
+pub use file_candles_storage::FileCandlesStorage;
+pub use sqlite_candles_storage::SqliteCandlesStorage;
+
+pub use candles_history::get_candles_history;
+pub use candles_history::fill_candles_gaps;
+pub use candles_history::get_last_candle_before;
+pub use candles_history::get_spread_history;
+
+pub use spread_storage::SpreadPersistentAzureStorage;
+
+pub use tick_validation::*;
+
+pub use azure_table_name_generators::*;
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, sync::Arc};
+
+use azure_core::Pageable;
+use azure_data_tables::{
+    operations::QueryEntityResponse,
+    prelude::{TableClient, TableServiceClient},
+};
+use futures::StreamExt;
+use tokio::sync::RwLock;
+
+use crate::models::{
+    CandleModelEntity, CandleType, DayRollovers, SpreadCandleEntity, SpreadCandleModel,
+    MAX_KEY_DATE,
+};
+
+use super::{get_spread_table_name, SpreadsStorage};
+
+pub struct SpreadPersistentAzureStorage {
+    table_service: Arc<TableServiceClient>,
+    cloud_tables: RwLock<HashMap<String, Arc<TableClient>>>,
+    day_rollovers: Arc<DayRollovers>,
+}
+
+impl SpreadPersistentAzureStorage {
+    pub fn new(table_service: Arc<TableServiceClient>, day_rollovers: Arc<DayRollovers>) -> Self {
+        Self {
+            table_service,
+            cloud_tables: RwLock::new(HashMap::new()),
+            day_rollovers,
+        }
+    }
+
+    async fn get_azure_table_storage(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+    ) -> Arc<TableClient> {
+        let table_name = get_spread_table_name(candle_type, instrument);
+
+        if let Some(table) = self.cloud_tables.read().await.get(&table_name) {
+            return table.clone();
+        }
+
+        let table_storage = Arc::new(self.table_service.table_client(&table_name));
+        let _ = table_storage.create().await;
+        self.cloud_tables
+            .write()
+            .await
+            .insert(table_name, table_storage.clone());
+
+        table_storage
+    }
+}
+
+#[async_trait::async_trait]
+impl SpreadsStorage for SpreadPersistentAzureStorage {
+    async fn delete_tables(&self, instrument: &str) {
+        for candle_type in CandleType::ALL {
+            let table_name = get_spread_table_name(candle_type, instrument);
+            self.cloud_tables.write().await.remove(&table_name);
+
+            if let Err(err) = self.table_service.table_client(&table_name).delete().await {
+                tracing::warn!("Can't delete spread table {}: {:?}", table_name, err);
+            }
+        }
+    }
+
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<SpreadCandleModel>,
+    ) {
+        if candles.is_empty() {
+            return;
+        }
+
+        let table_storage = self.get_azure_table_storage(instrument, candle_type).await;
+        let rollover = self.day_rollovers.get(instrument);
+
+        // cached candles hold the whole period, so they replace the persisted ones
+        let mut candles_by_keys: HashMap<(String, String), Vec<SpreadCandleModel>> = HashMap::new();
+        for candle in candles {
+            let key = (
+                CandleModelEntity::generate_partition_key(candle.datetime, candle_type, rollover),
+                CandleModelEntity::generate_row_key(candle.datetime, candle_type, rollover),
+            );
+            candles_by_keys.entry(key).or_default().push(candle);
+        }
+
+        for ((partition_key, row_key), candles) in candles_by_keys {
+            let entity_client = table_storage
+                .partition_key_client(&partition_key)
+                .entity_client(&row_key)
+                .unwrap();
+
+            let mut entity = match entity_client.get().await {
+                Ok(response) => response.entity,
+                Err(_) => SpreadCandleEntity::create(candle_type, candles[0].datetime, rollover),
+            };
+
+            let mut candles_dict = entity.get_candles(candle_type, rollover);
+            for candle in candles {
+                candles_dict.insert(candle.datetime, candle);
+            }
+            entity.set_candles(candles_dict, candle_type, rollover);
+
+            let res = entity_client.insert_or_replace(&entity).unwrap().await;
+
+            if let Err(err) = res {
+                tracing::error!(
+                    "Error while saving spread candles to Azure; Instrument: {}; Err: {:?}",
+                    instrument,
+                    err
+                );
+            }
+        }
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel> {
+        let mut result = Vec::new();
+        let table_storage = self.get_azure_table_storage(instrument, candle_type).await;
+        let rollover = self.day_rollovers.get(instrument);
+
+        // partition keys are date based, so they sort the same way as the dates
+        let filter = format!(
+            "PartitionKey ge '{}' and PartitionKey le '{}'",
+            CandleModelEntity::generate_partition_key(
+                u64::min(date_from, MAX_KEY_DATE),
+                candle_type,
+                rollover,
+            ),
+            CandleModelEntity::generate_partition_key(
+                u64::min(date_to, MAX_KEY_DATE),
+                candle_type,
+                rollover,
+            ),
+        );
+
+        let mut stream: Pageable<QueryEntityResponse<SpreadCandleEntity>, _> =
+            table_storage.query().filter(filter).into_stream();
+
+        while let Some(entity) = stream.next().await {
+            match entity {
+                Ok(entity) => {
+                    for entity in entity.entities {
+                        result.extend(
+                            entity
+                                .get_candles(candle_type, rollover)
+                                .into_values()
+                                .filter(|candle| {
+                                    candle.datetime >= date_from && candle.datetime < date_to
+                                }),
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "Error while reading spread candles from Azure; Err: {:?}",
+                        err
+                    );
+                }
+            }
+        }
+
+        result.sort_by_key(|candle| candle.datetime);
+        result
+    }
+}
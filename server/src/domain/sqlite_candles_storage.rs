@@ -0,0 +1,575 @@
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::{params, Connection};
+
+use crate::models::{round_price, CandleModel, CandleType, PriceSide, SpreadCandleModel};
+
+use super::{
+    instrument_storage::{format_candle_types, parse_candle_types},
+    CandlesStorage, InstrumentMetadata, InstrumentMetadataStorage, SpreadsStorage,
+};
+
+pub static DATABASE_FILE_NAME: &str = "candles.db";
+
+/// Candles in a SQLite database, a table per candle type keyed by (instrument, side, datetime).
+/// Saving merges the candles in place, so the stored candles are never read back to be updated.
+/// The spread candles have a table per candle type keyed by (instrument, datetime),
+/// the instrument metadata is in the `instruments` table.
+pub struct SqliteCandlesStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteCandlesStorage {
+    /// Opens the database in the `dir` directory, its tables are created when missing
+    pub fn new(dir: &Path) -> rusqlite::Result<Self> {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            tracing::error!("Can't create candles directory {:?}; Err: {:?}", dir, err);
+        }
+
+        Self::from_connection(Connection::open(dir.join(DATABASE_FILE_NAME))?)
+    }
+
+    pub fn from_connection(connection: Connection) -> rusqlite::Result<Self> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+
+        for candle_type in CandleType::ALL {
+            connection.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    instrument TEXT NOT NULL,
+                    side INTEGER NOT NULL,
+                    datetime INTEGER NOT NULL,
+                    open REAL NOT NULL,
+                    close REAL NOT NULL,
+                    high REAL NOT NULL,
+                    low REAL NOT NULL,
+                    ticks INTEGER NOT NULL,
+                    volume REAL,
+                    vwap REAL NOT NULL,
+                    open_time INTEGER NOT NULL,
+                    close_time INTEGER NOT NULL,
+                    PRIMARY KEY (instrument, side, datetime)
+                ) WITHOUT ROWID;",
+                get_sqlite_table_name(candle_type)
+            ))?;
+
+            connection.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    instrument TEXT NOT NULL,
+                    datetime INTEGER NOT NULL,
+                    min REAL NOT NULL,
+                    max REAL NOT NULL,
+                    avg REAL NOT NULL,
+                    close REAL NOT NULL,
+                    ticks INTEGER NOT NULL,
+                    PRIMARY KEY (instrument, datetime)
+                ) WITHOUT ROWID;",
+                get_sqlite_spread_table_name(candle_type)
+            ))?;
+        }
+
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS instruments (
+                instrument TEXT NOT NULL PRIMARY KEY,
+                first_seen INTEGER NOT NULL,
+                last_tick INTEGER NOT NULL,
+                tick_count INTEGER NOT NULL,
+                candle_types TEXT NOT NULL,
+                retired INTEGER NOT NULL
+            ) WITHOUT ROWID;",
+        )?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs the statements on a blocking thread, the errors are logged
+    async fn execute<T: Default + Send + 'static>(
+        &self,
+        operation: &'static str,
+        statements: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> T {
+        let connection = self.connection.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap();
+            statements(&mut connection)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) => {
+                tracing::error!("Error while {} in SQLite; Err: {:?}", operation, err);
+                T::default()
+            }
+            Err(err) => {
+                tracing::error!("Error while {} in SQLite; Err: {:?}", operation, err);
+                T::default()
+            }
+        }
+    }
+
+    async fn select(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<CandleModel> {
+        let instrument = instrument.to_string();
+        let sql = format!(
+            "SELECT datetime, open, close, high, low, ticks, volume, vwap, open_time, close_time
+            FROM {} WHERE instrument = ?1 AND side = ?2 AND datetime >= ?3 AND datetime < ?4
+            ORDER BY datetime",
+            get_sqlite_table_name(candle_type)
+        );
+
+        self.execute("reading candles", move |connection| {
+            let mut statement = connection.prepare_cached(&sql)?;
+            let rows = statement.query_map(
+                params![
+                    instrument,
+                    side as i32,
+                    to_sqlite_int(date_from),
+                    to_sqlite_int(date_to)
+                ],
+                |row| {
+                    Ok(CandleModel {
+                        datetime: row.get::<_, i64>(0)? as u64,
+                        open: row.get(1)?,
+                        close: row.get(2)?,
+                        high: row.get(3)?,
+                        low: row.get(4)?,
+                        ticks: row.get::<_, i64>(5)? as u64,
+                        volume: row.get(6)?,
+                        vwap: row.get(7)?,
+                        open_time: row.get::<_, i64>(8)? as u64,
+                        close_time: row.get::<_, i64>(9)? as u64,
+                        synthetic: false,
+                    })
+                },
+            )?;
+
+            rows.collect()
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl CandlesStorage for SqliteCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        candles: Vec<CandleModel>,
+        digits: Option<u32>,
+    ) {
+        if candles.is_empty() {
+            return;
+        }
+
+        let instrument = instrument.to_string();
+        // the stored candle is an earlier state of the saved one or a part of it after a merge,
+        // so the extremes, the earliest open and the latest close win
+        let sql = format!(
+            "INSERT INTO {} (instrument, side, datetime, open, close, high, low, ticks, volume, vwap,
+                open_time, close_time)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT (instrument, side, datetime) DO UPDATE SET
+                open = CASE WHEN excluded.open_time < open_time THEN excluded.open ELSE open END,
+                close = CASE WHEN excluded.close_time >= close_time THEN excluded.close ELSE close END,
+                vwap = CASE WHEN excluded.close_time >= close_time THEN excluded.vwap ELSE vwap END,
+                high = MAX(high, excluded.high),
+                low = MIN(low, excluded.low),
+                ticks = MAX(ticks, excluded.ticks),
+                volume = MAX(COALESCE(volume, excluded.volume), COALESCE(excluded.volume, volume)),
+                open_time = MIN(open_time, excluded.open_time),
+                close_time = MAX(close_time, excluded.close_time)",
+            get_sqlite_table_name(candle_type)
+        );
+
+        self.execute("saving candles", move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(&sql)?;
+
+                for candle in candles {
+                    statement.execute(params![
+                        instrument,
+                        side as i32,
+                        to_sqlite_int(candle.datetime),
+                        round_price(candle.open, digits),
+                        round_price(candle.close, digits),
+                        round_price(candle.high, digits),
+                        round_price(candle.low, digits),
+                        candle.ticks as i64,
+                        candle.volume,
+                        round_price(candle.vwap, digits),
+                        to_sqlite_int(candle.open_time),
+                        to_sqlite_int(candle.close_time),
+                    ])?;
+                }
+            }
+
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<CandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        self.select(instrument, side, candle_type, date_from, date_to)
+            .await
+    }
+
+    async fn get_async(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+        expiration_date: u64,
+        candle_type: CandleType,
+    ) -> Vec<CandleModel> {
+        self.select(instrument, side, candle_type, expiration_date, u64::MAX)
+            .await
+    }
+
+    async fn get_instruments(&self) -> Vec<String> {
+        let result: HashSet<String> = self
+            .execute("listing candles", |connection| {
+                let mut result = HashSet::new();
+
+                for candle_type in CandleType::ALL {
+                    let mut statement = connection.prepare_cached(&format!(
+                        "SELECT DISTINCT instrument FROM {}",
+                        get_sqlite_table_name(candle_type)
+                    ))?;
+                    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+                    for instrument in rows {
+                        result.insert(instrument?);
+                    }
+                }
+
+                Ok(result)
+            })
+            .await;
+
+        result.into_iter().collect()
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        let instrument = instrument.to_string();
+
+        self.execute("deleting candles", move |connection| {
+            let transaction = connection.transaction()?;
+
+            for candle_type in CandleType::ALL {
+                transaction.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE instrument = ?1",
+                        get_sqlite_table_name(candle_type)
+                    ),
+                    params![instrument],
+                )?;
+            }
+
+            transaction.commit()
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl SpreadsStorage for SqliteCandlesStorage {
+    async fn bulk_save(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        candles: Vec<SpreadCandleModel>,
+    ) {
+        if candles.is_empty() {
+            return;
+        }
+
+        let instrument = instrument.to_string();
+        let sql = format!(
+            "INSERT OR REPLACE INTO {} (instrument, datetime, min, max, avg, close, ticks)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            get_sqlite_spread_table_name(candle_type)
+        );
+
+        self.execute("saving spreads", move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(&sql)?;
+
+                for candle in candles {
+                    statement.execute(params![
+                        instrument,
+                        to_sqlite_int(candle.datetime),
+                        candle.min,
+                        candle.max,
+                        candle.avg,
+                        candle.close,
+                        candle.ticks as i64,
+                    ])?;
+                }
+            }
+
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn get_by_date_range(
+        &self,
+        instrument: &str,
+        candle_type: CandleType,
+        date_from: u64,
+        date_to: u64,
+    ) -> Vec<SpreadCandleModel> {
+        if date_from >= date_to {
+            return vec![];
+        }
+
+        let instrument = instrument.to_string();
+        let sql = format!(
+            "SELECT datetime, min, max, avg, close, ticks
+            FROM {} WHERE instrument = ?1 AND datetime >= ?2 AND datetime < ?3
+            ORDER BY datetime",
+            get_sqlite_spread_table_name(candle_type)
+        );
+
+        self.execute("reading spreads", move |connection| {
+            let mut statement = connection.prepare_cached(&sql)?;
+            let rows = statement.query_map(
+                params![instrument, to_sqlite_int(date_from), to_sqlite_int(date_to)],
+                |row| {
+                    let datetime = row.get::<_, i64>(0)? as u64;
+
+                    Ok(SpreadCandleModel {
+                        datetime,
+                        min: row.get(1)?,
+                        max: row.get(2)?,
+                        avg: row.get(3)?,
+                        close: row.get(4)?,
+                        ticks: row.get::<_, i64>(5)? as u64,
+                        close_time: datetime,
+                    })
+                },
+            )?;
+
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn delete_tables(&self, instrument: &str) {
+        let instrument = instrument.to_string();
+
+        self.execute("deleting spreads", move |connection| {
+            let transaction = connection.transaction()?;
+
+            for candle_type in CandleType::ALL {
+                transaction.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE instrument = ?1",
+                        get_sqlite_spread_table_name(candle_type)
+                    ),
+                    params![instrument],
+                )?;
+            }
+
+            transaction.commit()
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl InstrumentMetadataStorage for SqliteCandlesStorage {
+    async fn save(&self, instruments: Vec<(String, InstrumentMetadata)>) -> Vec<String> {
+        let saved: Vec<String> = instruments
+            .iter()
+            .map(|(instrument, _)| instrument.clone())
+            .collect();
+
+        let is_saved = self
+            .execute("saving instruments", move |connection| {
+                let transaction = connection.transaction()?;
+                {
+                    let mut statement = transaction.prepare_cached(
+                        "INSERT OR REPLACE INTO instruments
+                            (instrument, first_seen, last_tick, tick_count, candle_types, retired)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    )?;
+
+                    for (instrument, metadata) in instruments {
+                        statement.execute(params![
+                            instrument,
+                            to_sqlite_int(metadata.first_seen),
+                            to_sqlite_int(metadata.last_tick),
+                            to_sqlite_int(metadata.tick_count),
+                            format_candle_types(&metadata.candle_types),
+                            metadata.retired,
+                        ])?;
+                    }
+                }
+
+                transaction.commit()?;
+                Ok(true)
+            })
+            .await;
+
+        match is_saved {
+            true => vec![],
+            false => saved,
+        }
+    }
+
+    async fn delete(&self, instrument: &str) {
+        let instrument = instrument.to_string();
+
+        self.execute("deleting instrument", move |connection| {
+            connection.execute(
+                "DELETE FROM instruments WHERE instrument = ?1",
+                params![instrument],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load(&self) -> Vec<(String, InstrumentMetadata)> {
+        self.execute("reading instruments", |connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT instrument, first_seen, last_tick, tick_count, candle_types, retired
+                FROM instruments",
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    InstrumentMetadata {
+                        first_seen: row.get::<_, i64>(1)? as u64,
+                        last_tick: row.get::<_, i64>(2)? as u64,
+                        tick_count: row.get::<_, i64>(3)? as u64,
+                        candle_types: parse_candle_types(&row.get::<_, String>(4)?),
+                        retired: row.get(5)?,
+                    },
+                ))
+            })?;
+
+            rows.collect()
+        })
+        .await
+    }
+}
+
+fn get_sqlite_table_name(candle_type: CandleType) -> String {
+    format!("candles_{}", candle_type as i32)
+}
+
+fn get_sqlite_spread_table_name(candle_type: CandleType) -> String {
+    format!("spreads_{}", candle_type as i32)
+}
+
+// SQLite integers are signed, the dates and counts beyond i64 are saturated
+fn to_sqlite_int(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{
+        domain::{CandlesStorage, InstrumentMetadata, InstrumentMetadataStorage},
+        models::{CandleModel, CandleType, PriceSide},
+    };
+
+    use super::SqliteCandlesStorage;
+
+    #[tokio::test]
+    async fn test_upsert_merges_candles() {
+        let storage =
+            SqliteCandlesStorage::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let save = |candles: Vec<CandleModel>| {
+            storage.bulk_save(
+                "EURUSD",
+                PriceSide::Bid,
+                CandleType::Minute,
+                candles,
+                Some(4),
+            )
+        };
+
+        let mut candle = CandleModel::new_from_rate(1662558540, 1662558545, 1.00012, Some(1.0));
+        save(vec![candle.clone()]).await;
+
+        candle.update_by_rate(1.2, Some(2.0), 1662558550);
+        candle.update_by_rate(0.9, Some(1.0), 1662558555);
+        save(vec![candle.clone()]).await;
+
+        // an older state of the candle doesn't overwrite the newer one
+        let stale = CandleModel::new_from_rate(1662558540, 1662558545, 1.00012, Some(1.0));
+        save(vec![stale]).await;
+
+        let candles = storage
+            .get_by_date_range("EURUSD", PriceSide::Bid, CandleType::Minute, 0, u64::MAX)
+            .await;
+
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].open, 1.0001);
+        assert_eq!(candles[0].close, 0.9);
+        assert_eq!(candles[0].high, 1.2);
+        assert_eq!(candles[0].low, 0.9);
+        assert_eq!(candles[0].ticks, 3);
+        assert_eq!(candles[0].volume, Some(4.0));
+        assert_eq!(candles[0].close_time, 1662558555);
+
+        assert!(storage
+            .get_async("EURUSD", PriceSide::Ask, 0, CandleType::Minute)
+            .await
+            .is_empty());
+        assert_eq!(storage.get_instruments().await, vec!["EURUSD".to_string()]);
+
+        storage.delete_tables("EURUSD").await;
+        assert!(storage.get_instruments().await.is_empty());
+    }
+    #[tokio::test]
+    async fn test_delete_instrument() {
+        let storage =
+            SqliteCandlesStorage::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        let saved = vec![
+            ("EURUSD".to_string(), InstrumentMetadata::default()),
+            ("TESTUSD".to_string(), InstrumentMetadata::default()),
+        ];
+        assert!(storage.save(saved).await.is_empty());
+
+        storage.delete("TESTUSD").await;
+
+        let instruments = storage.load().await;
+        assert_eq!(instruments.len(), 1);
+        assert_eq!(instruments[0].0, "EURUSD");
+    }
+}
@@ -0,0 +1,160 @@
+use crate::models::{CandlesBidAsk, TickRejection};
+
+/// Single check of the validation stage every quote passes before the aggregation
+pub trait TickValidator: Send + Sync {
+    fn validate(
+        &self,
+        bid_ask: &CandlesBidAsk,
+        last_price: Option<&CandlesBidAsk>,
+    ) -> Result<(), TickRejection>;
+}
+
+/// Rejects NaN, zero, negative and crossed quotes
+pub struct PriceSanityValidator;
+
+impl TickValidator for PriceSanityValidator {
+    fn validate(
+        &self,
+        bid_ask: &CandlesBidAsk,
+        _last_price: Option<&CandlesBidAsk>,
+    ) -> Result<(), TickRejection> {
+        // NaN fails the comparison as well
+        if !(bid_ask.bid > 0.0 && bid_ask.ask > 0.0) {
+            return Err(TickRejection::InvalidPrice);
+        }
+
+        if bid_ask.bid > bid_ask.ask {
+            return Err(TickRejection::CrossedQuote);
+        }
+
+        Ok(())
+    }
+}
+
+/// Rejects quotes too far from the last accepted quote of the instrument
+pub struct SpikeValidator {
+    max_deviation_percent: f64,
+    /// The last quote older than this is not a reference any more
+    recent_price_sec: u64,
+}
+
+impl SpikeValidator {
+    pub fn new(max_deviation_percent: f64, recent_price_sec: u64) -> Self {
+        Self {
+            max_deviation_percent,
+            recent_price_sec,
+        }
+    }
+
+    fn is_spike(&self, rate: f64, last_rate: f64) -> bool {
+        (rate - last_rate).abs() / last_rate * 100.0 > self.max_deviation_percent
+    }
+}
+
+impl TickValidator for SpikeValidator {
+    fn validate(
+        &self,
+        bid_ask: &CandlesBidAsk,
+        last_price: Option<&CandlesBidAsk>,
+    ) -> Result<(), TickRejection> {
+        let last_price = match last_price {
+            Some(last_price) if last_price.date + self.recent_price_sec >= bid_ask.date => {
+                last_price
+            }
+            _ => return Ok(()),
+        };
+
+        if self.is_spike(bid_ask.bid, last_price.bid) || self.is_spike(bid_ask.ask, last_price.ask)
+        {
+            return Err(TickRejection::Spike);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct TickValidation {
+    validators: Vec<Box<dyn TickValidator>>,
+    /// Publish the rejected quotes to the quarantine topic
+    pub quarantine: bool,
+}
+
+impl TickValidation {
+    pub fn new(validators: Vec<Box<dyn TickValidator>>, quarantine: bool) -> Self {
+        Self {
+            validators,
+            quarantine,
+        }
+    }
+
+    /// Runs the validators in order, the first failed one rejects the quote
+    pub fn validate(
+        &self,
+        bid_ask: &CandlesBidAsk,
+        last_price: Option<&CandlesBidAsk>,
+    ) -> Result<(), TickRejection> {
+        for validator in self.validators.iter() {
+            validator.validate(bid_ask, last_price)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PriceSanityValidator, SpikeValidator, TickValidation};
+    use crate::models::{CandlesBidAsk, TickRejection};
+
+    fn bid_ask(date: u64, bid: f64, ask: f64) -> CandlesBidAsk {
+        CandlesBidAsk {
+            date,
+            instrument: "EURUSD".to_string(),
+            bid,
+            ask,
+            volume: None,
+        }
+    }
+
+    #[test]
+    fn test_tick_validation() {
+        let validation = TickValidation::new(
+            vec![
+                Box::new(PriceSanityValidator),
+                Box::new(SpikeValidator::new(5.0, 60)),
+            ],
+            false,
+        );
+        let last_price = bid_ask(1662559404, 1.0, 1.1);
+
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, 1.01, 1.11), Some(&last_price)),
+            Ok(())
+        );
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, f64::NAN, 1.1), Some(&last_price)),
+            Err(TickRejection::InvalidPrice)
+        );
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, 0.0, 1.1), None),
+            Err(TickRejection::InvalidPrice)
+        );
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, -1.0, 1.1), None),
+            Err(TickRejection::InvalidPrice)
+        );
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, 1.2, 1.1), None),
+            Err(TickRejection::CrossedQuote)
+        );
+        assert_eq!(
+            validation.validate(&bid_ask(1662559405, 1.0, 1.5), Some(&last_price)),
+            Err(TickRejection::Spike)
+        );
+        // the last price is not recent any more
+        assert_eq!(
+            validation.validate(&bid_ask(1662559500, 1.0, 1.5), Some(&last_price)),
+            Ok(())
+        );
+    }
+}
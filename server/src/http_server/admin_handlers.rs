@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use hyper::{Body, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppContext,
+    domain::{merge_instrument_history, retire_instrument},
+};
+
+use super::server::{empty_response, json_response};
+
+#[derive(Debug, Deserialize)]
+pub struct MergeQuery {
+    pub alias: String,
+    pub canonical: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeResult {
+    pub merged: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetireQuery {
+    pub instrument: String,
+    #[serde(default)]
+    pub purge: bool,
+}
+
+/// Merges the history of an old instrument name into the canonical one, once per alias
+pub async fn merge(context: &Arc<AppContext>, query: &str) -> Response<Body> {
+    let query: MergeQuery = match serde_urlencoded::from_str(query) {
+        Ok(query) => query,
+        Err(_) => return empty_response(StatusCode::BAD_REQUEST),
+    };
+
+    if query.alias == query.canonical {
+        return empty_response(StatusCode::BAD_REQUEST);
+    }
+
+    if !context.instrument_storage.contains(&query.alias).await
+        || !context.instrument_storage.contains(&query.canonical).await
+    {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+
+    // a disabled or retired canonical instrument would hide the merged history
+    if !context
+        .instrument_dictionary
+        .is_enabled(&query.canonical)
+        .await
+    {
+        return empty_response(StatusCode::CONFLICT);
+    }
+
+    match merge_instrument_history(context, &query.alias, &query.canonical).await {
+        Some(merged) => json_response(&MergeResult { merged }),
+        None => empty_response(StatusCode::CONFLICT),
+    }
+}
+
+/// Retires an instrument, `purge=true` also deletes its stored history
+pub async fn retire(context: &Arc<AppContext>, query: &str) -> Response<Body> {
+    let query: RetireQuery = match serde_urlencoded::from_str(query) {
+        Ok(query) => query,
+        Err(_) => return empty_response(StatusCode::BAD_REQUEST),
+    };
+
+    match retire_instrument(context, &query.instrument, query.purge).await {
+        true => empty_response(StatusCode::OK),
+        false => empty_response(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,6 @@
+mod admin_handlers;
+mod server;
+mod udf_handlers;
+mod udf_models;
+
+pub use server::{start_admin_http_server, start_http_server};
@@ -0,0 +1,108 @@
+use std::{convert::Infallible, future::Future, net::SocketAddr, sync::Arc};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+
+use crate::app::AppContext;
+
+use super::{admin_handlers, udf_handlers};
+
+pub async fn start_http_server(context: Arc<AppContext>, port: u16) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    serve(context, addr, "Http", handle_request).await;
+}
+
+/// Admin operations change the stored history, so they are served on their own listener
+/// that is meant to be bound to an internal address only
+pub async fn start_admin_http_server(context: Arc<AppContext>, addr: SocketAddr) {
+    serve(context, addr, "Admin http", handle_admin_request).await;
+}
+
+async fn serve<F, Fut>(context: Arc<AppContext>, addr: SocketAddr, name: &str, handler: F)
+where
+    F: Fn(Arc<AppContext>, Request<Body>) -> Fut + Copy + Send + Sync + 'static,
+    Fut: Future<Output = Response<Body>> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_| {
+        let context = context.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let context = context.clone();
+                async move { Ok::<_, Infallible>(handler(context, request).await) }
+            }))
+        }
+    });
+
+    tracing::info!("{} server is listening on {}", name, addr);
+
+    if let Err(err) = Server::bind(&addr).serve(make_service).await {
+        tracing::error!("{} server error: {:?}", name, err);
+    }
+}
+
+async fn handle_admin_request(context: Arc<AppContext>, request: Request<Body>) -> Response<Body> {
+    let query = request.uri().query().unwrap_or("");
+
+    if request.method() != Method::POST {
+        return empty_response(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    match request.uri().path() {
+        "/admin/merge" => admin_handlers::merge(&context, query).await,
+        "/admin/retire" => admin_handlers::retire(&context, query).await,
+        _ => empty_response(StatusCode::NOT_FOUND),
+    }
+}
+
+async fn handle_request(context: Arc<AppContext>, request: Request<Body>) -> Response<Body> {
+    let query = request.uri().query().unwrap_or("");
+
+    // the datafeed is read only
+    if request.method() != Method::GET {
+        return empty_response(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    match request.uri().path() {
+        "/config" => udf_handlers::get_config(),
+        "/time" => udf_handlers::get_time(),
+        "/symbols" => udf_handlers::get_symbol(&context, query).await,
+        "/search" => udf_handlers::search(&context, query).await,
+        "/history" => udf_handlers::get_history(&context, query).await,
+        "/metrics" => text_response(context.tick_metrics.to_prometheus().await),
+        _ => empty_response(StatusCode::NOT_FOUND),
+    }
+}
+
+pub fn json_response<T: serde::Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(json) => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::from(json))
+            .unwrap(),
+        Err(err) => {
+            tracing::error!("Can't serialize http response: {:?}", err);
+            empty_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub fn text_response(text: String) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/plain")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(text))
+        .unwrap()
+}
+
+pub fn empty_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use hyper::{Body, Response};
+
+use crate::{
+    app::AppContext,
+    domain::{fill_candles_gaps, get_candles_history, get_last_candle_before},
+    models::{CandleType, PriceSide},
+};
+
+use super::{
+    server::{json_response, text_response},
+    udf_models::{
+        UdfConfig, UdfError, UdfHistory, UdfHistoryQuery, UdfSearchItem, UdfSearchQuery,
+        UdfSymbolInfo, UdfSymbolQuery,
+    },
+};
+
+const SUPPORTED_RESOLUTIONS: [&str; 9] = ["1", "5", "15", "30", "60", "240", "1D", "1W", "1M"];
+const INTRADAY_MULTIPLIERS: [&str; 6] = ["1", "5", "15", "30", "60", "240"];
+const DEFAULT_PRICE_SCALE: u64 = 100_000;
+const DEFAULT_SEARCH_LIMIT: usize = 30;
+const SYMBOL_TYPE: &str = "crypto";
+
+pub fn resolution_to_candle_type(resolution: &str) -> Option<CandleType> {
+    match resolution {
+        "1" => Some(CandleType::Minute),
+        "5" => Some(CandleType::Minute5),
+        "15" => Some(CandleType::Minute15),
+        "30" => Some(CandleType::Minute30),
+        "60" => Some(CandleType::Hour),
+        "240" => Some(CandleType::Hour4),
+        "D" | "1D" => Some(CandleType::Day),
+        "W" | "1W" => Some(CandleType::Week),
+        "M" | "1M" => Some(CandleType::Month),
+        _ => None,
+    }
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+pub fn get_config() -> Response<Body> {
+    json_response(&UdfConfig {
+        supported_resolutions: to_strings(&SUPPORTED_RESOLUTIONS),
+        supports_search: true,
+        supports_group_request: false,
+        supports_marks: false,
+        supports_timescale_marks: false,
+        supports_time: true,
+    })
+}
+
+pub fn get_time() -> Response<Body> {
+    text_response(chrono::Utc::now().timestamp().to_string())
+}
+
+pub async fn get_symbol(context: &Arc<AppContext>, query: &str) -> Response<Body> {
+    let query: UdfSymbolQuery = match serde_urlencoded::from_str(query) {
+        Ok(query) => query,
+        Err(_) => return json_response(&UdfError::new("invalid_request")),
+    };
+
+    if !context.instrument_storage.is_active(&query.symbol).await {
+        return json_response(&UdfError::new("unknown_symbol"));
+    }
+
+    let pricescale = match context.instrument_storage.get_digits(&query.symbol).await {
+        Some(digits) => 10u64.pow(digits),
+        None => DEFAULT_PRICE_SCALE,
+    };
+
+    json_response(&UdfSymbolInfo {
+        name: query.symbol.clone(),
+        ticker: query.symbol.clone(),
+        description: query.symbol,
+        symbol_type: SYMBOL_TYPE.to_string(),
+        session: "24x7".to_string(),
+        timezone: "Etc/UTC".to_string(),
+        exchange: "".to_string(),
+        listed_exchange: "".to_string(),
+        minmov: 1,
+        pricescale,
+        has_intraday: true,
+        has_daily: true,
+        has_weekly_and_monthly: true,
+        supported_resolutions: to_strings(&SUPPORTED_RESOLUTIONS),
+        intraday_multipliers: to_strings(&INTRADAY_MULTIPLIERS),
+        data_status: "streaming".to_string(),
+    })
+}
+
+pub async fn search(context: &Arc<AppContext>, query: &str) -> Response<Body> {
+    let query: UdfSearchQuery = match serde_urlencoded::from_str(query) {
+        Ok(query) => query,
+        Err(_) => return json_response(&UdfError::new("invalid_request")),
+    };
+
+    let search = query.query.to_uppercase();
+    let mut instruments: Vec<String> = context
+        .instrument_storage
+        .get_active_instruments()
+        .await
+        .into_iter()
+        .filter(|instrument| instrument.to_uppercase().contains(&search))
+        .collect();
+    instruments.sort();
+
+    let result: Vec<UdfSearchItem> = instruments
+        .into_iter()
+        .take(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map(|instrument| UdfSearchItem {
+            symbol: instrument.clone(),
+            full_name: instrument.clone(),
+            description: instrument.clone(),
+            exchange: "".to_string(),
+            ticker: instrument,
+            symbol_type: SYMBOL_TYPE.to_string(),
+        })
+        .collect();
+
+    json_response(&result)
+}
+
+pub async fn get_history(context: &Arc<AppContext>, query: &str) -> Response<Body> {
+    let query: UdfHistoryQuery = match serde_urlencoded::from_str(query) {
+        Ok(query) => query,
+        Err(_) => return json_response(&UdfError::new("invalid_request")),
+    };
+
+    let candle_type = match resolution_to_candle_type(&query.resolution) {
+        Some(candle_type) => candle_type,
+        None => return json_response(&UdfError::new("unsupported_resolution")),
+    };
+
+    let (date_from, date_to) = match query.get_date_range() {
+        Some(range) => range,
+        None => return json_response(&UdfError::new("invalid_range")),
+    };
+
+    if !context.instrument_storage.is_active(&query.symbol).await {
+        return json_response(&UdfError::new("unknown_symbol"));
+    }
+
+    let mut candles = get_candles_history(
+        &context.cache,
+        context.candles_storage.as_ref(),
+        &query.symbol,
+        candle_type,
+        PriceSide::Bid,
+        date_from,
+        date_to,
+    )
+    .await;
+
+    if query.fill_gaps {
+        candles = match fill_candles_gaps(
+            &context.cache,
+            context.candles_storage.as_ref(),
+            context.session_calendars.get(&query.symbol),
+            &query.symbol,
+            candle_type,
+            PriceSide::Bid,
+            candles,
+            date_from,
+            date_to,
+        )
+        .await
+        {
+            Ok(candles) => candles,
+            Err(_) => return json_response(&UdfError::new("too_many_periods")),
+        };
+    }
+
+    if let Some(countback) = query.countback {
+        if candles.len() > countback {
+            candles.drain(..candles.len() - countback);
+        }
+    }
+
+    if candles.is_empty() {
+        let next_time = get_last_candle_before(
+            &context.cache,
+            context.candles_storage.as_ref(),
+            &query.symbol,
+            candle_type,
+            PriceSide::Bid,
+            query.from,
+        )
+        .await
+        .map(|candle| candle.datetime);
+
+        return json_response(&UdfHistory::NoData { next_time });
+    }
+
+    json_response(&UdfHistory::Ok {
+        t: candles.iter().map(|candle| candle.datetime).collect(),
+        o: candles.iter().map(|candle| candle.open).collect(),
+        h: candles.iter().map(|candle| candle.high).collect(),
+        l: candles.iter().map(|candle| candle.low).collect(),
+        c: candles.iter().map(|candle| candle.close).collect(),
+        v: candles
+            .iter()
+            .any(|candle| candle.volume.is_some())
+            .then(|| {
+                candles
+                    .iter()
+                    .map(|candle| candle.volume.unwrap_or(0.0))
+                    .collect()
+            }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolution_to_candle_type;
+    use crate::{
+        http_server::udf_models::{UdfHistory, UdfHistoryQuery},
+        models::CandleType,
+    };
+
+    #[test]
+    fn test_resolution_mapping() {
+        assert_eq!(resolution_to_candle_type("1"), Some(CandleType::Minute));
+        assert_eq!(resolution_to_candle_type("60"), Some(CandleType::Hour));
+        assert_eq!(resolution_to_candle_type("1D"), Some(CandleType::Day));
+        assert_eq!(resolution_to_candle_type("D"), Some(CandleType::Day));
+        assert_eq!(resolution_to_candle_type("1W"), Some(CandleType::Week));
+        assert_eq!(resolution_to_candle_type("1M"), Some(CandleType::Month));
+        assert_eq!(resolution_to_candle_type("5"), Some(CandleType::Minute5));
+        assert_eq!(resolution_to_candle_type("240"), Some(CandleType::Hour4));
+        assert_eq!(resolution_to_candle_type("120"), None);
+    }
+
+    #[test]
+    fn test_history_date_range() {
+        let query = |query: &str| serde_urlencoded::from_str::<UdfHistoryQuery>(query).unwrap();
+
+        let range = query("symbol=EURUSD&resolution=1&from=1662559380&to=1662559440");
+        assert_eq!(range.get_date_range(), Some((1662559380, 1662559441)));
+
+        let single = query("symbol=EURUSD&resolution=1&from=1662559380&to=1662559380");
+        assert_eq!(single.get_date_range(), Some((1662559380, 1662559381)));
+
+        let reversed = query("symbol=EURUSD&resolution=1&from=1662559440&to=1662559380");
+        assert_eq!(reversed.get_date_range(), None);
+
+        let max = format!("symbol=EURUSD&resolution=1&from=0&to={}", u64::MAX);
+        assert_eq!(query(&max).get_date_range(), Some((0, u64::MAX)));
+    }
+
+    #[test]
+    fn test_history_serialization() {
+        let no_data = serde_json::to_string(&UdfHistory::NoData {
+            next_time: Some(1662559380),
+        })
+        .unwrap();
+        assert_eq!(no_data, r#"{"s":"no_data","nextTime":1662559380}"#);
+
+        let no_data = serde_json::to_string(&UdfHistory::NoData { next_time: None }).unwrap();
+        assert_eq!(no_data, r#"{"s":"no_data"}"#);
+
+        let ok = serde_json::to_string(&UdfHistory::Ok {
+            t: vec![1662559380],
+            o: vec![1.1],
+            h: vec![1.3],
+            l: vec![1.0],
+            c: vec![1.2],
+            v: Some(vec![2.5]),
+        })
+        .unwrap();
+        assert_eq!(
+            ok,
+            r#"{"s":"ok","t":[1662559380],"o":[1.1],"h":[1.3],"l":[1.0],"c":[1.2],"v":[2.5]}"#
+        );
+    }
+}
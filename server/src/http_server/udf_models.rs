@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct UdfConfig {
+    pub supported_resolutions: Vec<String>,
+    pub supports_search: bool,
+    pub supports_group_request: bool,
+    pub supports_marks: bool,
+    pub supports_timescale_marks: bool,
+    pub supports_time: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UdfSymbolInfo {
+    pub name: String,
+    pub ticker: String,
+    pub description: String,
+    #[serde(rename = "type")]
+    pub symbol_type: String,
+    pub session: String,
+    pub timezone: String,
+    pub exchange: String,
+    pub listed_exchange: String,
+    pub minmov: u32,
+    pub pricescale: u64,
+    pub has_intraday: bool,
+    pub has_daily: bool,
+    pub has_weekly_and_monthly: bool,
+    pub supported_resolutions: Vec<String>,
+    pub intraday_multipliers: Vec<String>,
+    pub data_status: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UdfSearchItem {
+    pub symbol: String,
+    pub full_name: String,
+    pub description: String,
+    pub exchange: String,
+    pub ticker: String,
+    #[serde(rename = "type")]
+    pub symbol_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UdfError {
+    pub s: String,
+    pub errmsg: String,
+}
+
+impl UdfError {
+    pub fn new(errmsg: &str) -> Self {
+        Self {
+            s: "error".to_string(),
+            errmsg: errmsg.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "s")]
+pub enum UdfHistory {
+    #[serde(rename = "ok")]
+    Ok {
+        t: Vec<u64>,
+        o: Vec<f64>,
+        h: Vec<f64>,
+        l: Vec<f64>,
+        c: Vec<f64>,
+        /// Left out when none of the candles has a volume
+        #[serde(skip_serializing_if = "Option::is_none")]
+        v: Option<Vec<f64>>,
+    },
+    #[serde(rename = "no_data")]
+    NoData {
+        #[serde(rename = "nextTime", skip_serializing_if = "Option::is_none")]
+        next_time: Option<u64>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UdfSymbolQuery {
+    pub symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UdfSearchQuery {
+    #[serde(default)]
+    pub query: String,
+    pub limit: Option<usize>,
+}
+
+/// The [from, to] range is inclusive, dates are unix timestamps in seconds
+#[derive(Debug, Deserialize)]
+pub struct UdfHistoryQuery {
+    pub symbol: String,
+    pub resolution: String,
+    pub from: u64,
+    pub to: u64,
+    pub countback: Option<usize>,
+    /// Emit flat synthetic candles for the periods without ticks
+    #[serde(default)]
+    pub fill_gaps: bool,
+}
+
+impl UdfHistoryQuery {
+    /// The [from, to) range of the candle lookups, None when `from` is after `to`
+    pub fn get_date_range(&self) -> Option<(u64, u64)> {
+        match self.from <= self.to {
+            true => Some((self.from, self.to.saturating_add(1))),
+            false => None,
+        }
+    }
+}
@@ -8,3 +8,4 @@ pub mod caches;
 pub mod models;
 pub mod no_sql;
 pub mod subscribers;
+pub mod http_server;
@@ -2,11 +2,16 @@ use rust_service_sdk::app::app_ctx::InitGrpc;
 use rust_service_sdk::application::Application;
 use service_candle_writer::app::AppContext;
 use service_candle_writer::domain::{persist_candles, restore_candles};
+use service_candle_writer::http_server::{start_admin_http_server, start_http_server};
 use service_candle_writer::settings_model::SettingsModel;
 
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 
+const INSTRUMENT_DICTIONARY_REFRESH_MS: u64 = 10_000;
+// how long the startup waits for the spot instruments table before giving up
+const INSTRUMENT_DICTIONARY_TIMEOUT_SEC: u64 = 300;
+
 #[tokio::main]
 async fn main() {
     let mut application = Application::<AppContext, SettingsModel>::init(AppContext::new).await;
@@ -30,6 +35,19 @@ async fn main() {
 
         //RESTORE CANDLES CACHE
         let mut latest_timestamp = restore_candles(&context.clone()).await;
+        //LOAD INSTRUMENT DICTIONARY, ticks of unknown instruments are rejected until it is loaded
+        let started = std::time::Instant::now();
+        while !context.instrument_dictionary.refresh().await {
+            if started.elapsed().as_secs() >= INSTRUMENT_DICTIONARY_TIMEOUT_SEC {
+                tracing::error!(
+                    "Instrument dictionary is not received in {} sec, is the spot instruments table missing?",
+                    INSTRUMENT_DICTIONARY_TIMEOUT_SEC
+                );
+                std::process::exit(1);
+            }
+            tracing::warn!("Instrument dictionary is not received yet");
+            tokio::time::sleep(std::time::Duration::from_millis(1_000)).await;
+        }
         //START SERVICE BUS
         context.service_bus.start().await;
         loop {
@@ -122,7 +140,38 @@ async fn main() {
         }
     }); */
 
-    let mut running_tasks = vec![persist_candels, /* check_size */];
+    let context = application.context.clone();
+    let cancellation_token = token.clone();
+    let refresh_instruments = tokio::spawn(async move {
+        loop {
+            if cancellation_token.is_cancelled() {
+                return Ok(());
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(
+                INSTRUMENT_DICTIONARY_REFRESH_MS,
+            ))
+            .await;
+            context.instrument_dictionary.refresh().await;
+        }
+    });
+
+    let mut running_tasks = vec![persist_candels, refresh_instruments, /* check_size */];
+
+    if let Some(port) = application.context.settings.inner.http_port {
+        let context = application.context.clone();
+        running_tasks.push(tokio::spawn(async move {
+            start_http_server(context, port).await;
+            Ok(())
+        }));
+    }
+
+    if let Some(addr) = application.context.settings.inner.admin_http_address {
+        let context = application.context.clone();
+        running_tasks.push(tokio::spawn(async move {
+            start_admin_http_server(context, addr).await;
+            Ok(())
+        }));
+    }
 
     application
         .wait_for_termination(
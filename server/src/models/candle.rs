@@ -1,6 +1,5 @@
 use serde::{Deserialize, Serialize};
-
-use super::CandleType;
+use service_candle_writer_generated_proto::CandleGrpcModel;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CandleModel {
@@ -9,23 +8,81 @@ pub struct CandleModel {
     pub high: f64,
     pub low: f64,
     pub datetime: u64,
+    #[serde(default)]
+    pub ticks: u64,
+    /// None while none of the ticks carried a volume
+    #[serde(default)]
+    pub volume: Option<f64>,
+    /// Volume weighted average price, the close price while there is no volume
+    #[serde(default)]
+    pub vwap: f64,
+    /// Timestamp of the earliest tick, the open price comes from it
+    #[serde(default)]
+    pub open_time: u64,
+    /// Timestamp of the latest tick, the close price comes from it
+    #[serde(default)]
+    pub close_time: u64,
+    /// Flat candle filling a period without ticks, never persisted
+    #[serde(default)]
+    pub synthetic: bool,
 }
 
 impl CandleModel {
-    pub fn new_from_rate(candle_type: CandleType, date: u64, rate: f64) -> Self {
-        let date = candle_type.format_date_by_type(date);
-
+    /// Candle of the period starting at `datetime` opened by the tick at `date`
+    pub fn new_from_rate(datetime: u64, date: u64, rate: f64, volume: Option<f64>) -> Self {
         Self {
             open: rate,
             close: rate,
             high: rate,
             low: rate,
-            datetime: date,
+            datetime,
+            ticks: 1,
+            volume,
+            vwap: rate,
+            open_time: date,
+            close_time: date,
+            synthetic: false,
+        }
+    }
+
+    pub fn new_synthetic(datetime: u64, close: f64) -> Self {
+        Self {
+            open: close,
+            close,
+            high: close,
+            low: close,
+            datetime,
+            ticks: 0,
+            volume: None,
+            vwap: close,
+            open_time: datetime,
+            close_time: datetime,
+            synthetic: true,
         }
     }
 
-    pub fn update_by_rate(&mut self, rate: f64) {
-        self.close = rate;
+    /// Ticks may arrive out of order, open and close follow the tick timestamps
+    pub fn update_by_rate(&mut self, rate: f64, volume: Option<f64>, date: u64) {
+        if date < self.open_time {
+            self.open = rate;
+            self.open_time = date;
+        }
+
+        if date >= self.close_time {
+            self.close = rate;
+            self.close_time = date;
+        }
+
+        self.ticks += 1;
+
+        let (current_volume, tick_volume) = (self.volume.unwrap_or(0.0), volume.unwrap_or(0.0));
+        let total_volume = current_volume + tick_volume;
+        self.vwap = if total_volume > 0.0 {
+            (self.vwap * current_volume + rate * tick_volume) / total_volume
+        } else {
+            rate
+        };
+        self.volume = add_volumes(self.volume, volume);
 
         if self.high < rate {
             self.high = rate;
@@ -35,4 +92,96 @@ impl CandleModel {
             self.low = rate;
         }
     }
+
+    /// Combines two candles of the same period, e.g. of two names of one instrument
+    pub fn merge(&self, other: &CandleModel) -> CandleModel {
+        // 0 is the open time of the candles persisted before it was tracked
+        let other_opened_first =
+            other.open_time != 0 && (self.open_time == 0 || other.open_time < self.open_time);
+        let (open, open_time) = match other_opened_first {
+            true => (other.open, other.open_time),
+            false => (self.open, self.open_time),
+        };
+        let (close, close_time) = match other.close_time > self.close_time {
+            true => (other.close, other.close_time),
+            false => (self.close, self.close_time),
+        };
+
+        let (self_volume, other_volume) = (self.volume.unwrap_or(0.0), other.volume.unwrap_or(0.0));
+        let total_volume = self_volume + other_volume;
+        let vwap = if total_volume > 0.0 {
+            (self.vwap * self_volume + other.vwap * other_volume) / total_volume
+        } else {
+            close
+        };
+
+        CandleModel {
+            open,
+            close,
+            high: self.high.max(other.high),
+            low: self.low.min(other.low),
+            datetime: self.datetime,
+            ticks: self.ticks + other.ticks,
+            volume: add_volumes(self.volume, other.volume),
+            vwap,
+            open_time,
+            close_time,
+            synthetic: false,
+        }
+    }
+}
+
+impl From<CandleModel> for CandleGrpcModel {
+    fn from(candle: CandleModel) -> Self {
+        CandleGrpcModel {
+            datetime: candle.datetime,
+            open: candle.open,
+            close: candle.close,
+            high: candle.high,
+            low: candle.low,
+            ticks: candle.ticks,
+            volume: candle.volume.unwrap_or(0.0),
+            vwap: candle.vwap,
+            synthetic: candle.synthetic,
+            has_volume: candle.volume.is_some(),
+        }
+    }
+}
+
+/// The volume stays unknown only while both of them are
+pub fn add_volumes(volume: Option<f64>, other: Option<f64>) -> Option<f64> {
+    match (volume, other) {
+        (None, None) => None,
+        (volume, other) => Some(volume.unwrap_or(0.0) + other.unwrap_or(0.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CandleModel;
+
+    #[test]
+    fn test_merge() {
+        // the old name traded until 14:03:20, the new one from 14:03:30
+        let mut old = CandleModel::new_from_rate(1662559380, 1662559385, 1.1, Some(1.0));
+        old.update_by_rate(1.3, Some(1.0), 1662559400);
+        let mut new = CandleModel::new_from_rate(1662559380, 1662559410, 1.2, Some(2.0));
+        new.update_by_rate(1.0, None, 1662559430);
+
+        let merged = new.merge(&old);
+
+        assert_eq!(merged.open, 1.1);
+        assert_eq!(merged.open_time, 1662559385);
+        assert_eq!(merged.close, 1.0);
+        assert_eq!(merged.close_time, 1662559430);
+        assert_eq!(merged.high, 1.3);
+        assert_eq!(merged.low, 1.0);
+        assert_eq!(merged.ticks, 4);
+        assert_eq!(merged.volume, Some(4.0));
+        assert!((merged.vwap - 1.2).abs() < 1e-9);
+
+        let quote = CandleModel::new_from_rate(1662559380, 1662559385, 1.1, None);
+        assert_eq!(quote.merge(&quote).volume, None);
+        assert_eq!(quote.merge(&new).volume, Some(2.0));
+    }
 }
@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
 
-use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 
-use super::{CandleModel, CandleType};
+use super::{CandleModel, CandleType, DayRollover};
+
+/// 9999-12-31 23:59:59, the latest date a partition key can be generated for
+pub const MAX_KEY_DATE: u64 = 253_402_300_799;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CandleModelEntity {
@@ -17,34 +20,49 @@ pub struct CandleModelEntity {
 }
 
 impl CandleModelEntity {
-
-    pub fn create(candle_type: CandleType, candle: CandleModel) -> Self {
+    pub fn create(candle_type: CandleType, candle: CandleModel, rollover: DayRollover) -> Self {
         return Self {
-            partition_key : CandleModelEntity::generate_partition_key(candle.datetime, candle_type),
-            row_key: CandleModelEntity::generate_row_key(candle.datetime, candle_type),
+            partition_key: CandleModelEntity::generate_partition_key(
+                candle.datetime,
+                candle_type,
+                rollover,
+            ),
+            row_key: CandleModelEntity::generate_row_key(candle.datetime, candle_type, rollover),
             data: "".to_string(),
         };
     }
 
-    pub fn get_candles(&self, candle_type: CandleType) -> BTreeMap<u64, CandleModel> {
+    pub fn get_candles(
+        &self,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> BTreeMap<u64, CandleModel> {
         return CandleModelEntity::data_string_to_candle_grpc_model(
             &self.data,
             candle_type,
             &self.partition_key,
             &self.row_key,
+            rollover,
         );
     }
 
     pub fn set_candles(
         &mut self,
         items: BTreeMap<u64, CandleModel>,
-        _digits: i32,
+        digits: Option<u32>,
         candle_type: CandleType,
+        rollover: DayRollover,
     ) {
-        self.data = CandleModelEntity::to_data_string(items, candle_type);
+        self.data = CandleModelEntity::to_data_string(items, digits, candle_type, rollover);
     }
 
-    pub fn generate_partition_key(date_time: u64, candle_type: CandleType) -> String {
+    /// Daily candles are keyed by their trading date, see `DayRollover::to_key_date`
+    pub fn generate_partition_key(
+        date_time: u64,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> String {
+        let date_time = rollover.to_key_date(candle_type, date_time);
         let date_time = Utc.timestamp_millis_opt((date_time * 1000) as i64).unwrap();
         return match candle_type {
             CandleType::Minute => format!(
@@ -53,33 +71,66 @@ impl CandleModelEntity {
                 date_time.format("%m"),
                 date_time.format("%d"),
             ),
-            CandleType::Hour => format!("{}{}", date_time.format("%Y"), date_time.format("%m"),),
+            CandleType::Hour
+            | CandleType::Minute5
+            | CandleType::Minute15
+            | CandleType::Minute30 => {
+                format!("{}{}", date_time.format("%Y"), date_time.format("%m"),)
+            }
+            CandleType::Hour4 => date_time.format("%Y").to_string(),
             CandleType::Day => date_time.format("%Y").to_string(),
+            CandleType::Week => date_time.format("%G").to_string(),
             CandleType::Month => date_time.format("%Y").to_string(),
         };
     }
 
-    pub fn generate_row_key(date_time: u64, candle_type: CandleType) -> String {
+    pub fn generate_row_key(
+        date_time: u64,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> String {
+        let date_time = rollover.to_key_date(candle_type, date_time);
         let date_time = Utc.timestamp_millis_opt((date_time * 1000) as i64).unwrap();
         return match candle_type {
             CandleType::Minute => date_time.format("%H").to_string(),
-            CandleType::Hour => date_time.format("%d").to_string(),
+            CandleType::Hour
+            | CandleType::Minute5
+            | CandleType::Minute15
+            | CandleType::Minute30 => date_time.format("%d").to_string(),
+            CandleType::Hour4 => date_time.format("%m").to_string(),
             CandleType::Day => date_time.format("%m").to_string(),
+            CandleType::Week => date_time.format("%V").to_string(),
             CandleType::Month => date_time.format("%Y").to_string(),
         };
     }
 
-    pub fn to_date_part_string(datetime: u64, candle_type: CandleType) -> String {
+    pub fn to_date_part_string(
+        datetime: u64,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> String {
+        let datetime = rollover.to_key_date(candle_type, datetime);
         let dt = Utc.timestamp_millis_opt((datetime * 1000) as i64).unwrap();
         return match candle_type {
             CandleType::Month => dt.format("%m").to_string(),
             CandleType::Day => dt.format("%d").to_string(),
+            CandleType::Week => dt.format("%V").to_string(),
             CandleType::Minute => dt.format("%M").to_string(),
             CandleType::Hour => dt.format("%H").to_string(),
+            CandleType::Minute5 | CandleType::Minute15 | CandleType::Minute30 => {
+                dt.format("%H%M").to_string()
+            }
+            CandleType::Hour4 => dt.format("%d%H").to_string(),
         };
     }
 
-    pub fn to_data_string(items: BTreeMap<u64, CandleModel>, candle_type: CandleType) -> String {
+    /// Prices are rounded to `digits`, kept as they are when the precision is unknown
+    pub fn to_data_string(
+        items: BTreeMap<u64, CandleModel>,
+        digits: Option<u32>,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> String {
         let mut result = String::with_capacity(20);
         for (datetime, candle) in items.into_iter() {
             if result.len() > 0 {
@@ -91,15 +142,29 @@ impl CandleModelEntity {
             concat.push_str(&CandleModelEntity::to_date_part_string(
                 datetime,
                 candle_type,
+                rollover,
             ));
             concat.push(';');
-            concat.push_str(&candle.open.to_string());
+            concat.push_str(&format_price(candle.open, digits));
+            concat.push(';');
+            concat.push_str(&format_price(candle.close, digits));
             concat.push(';');
-            concat.push_str(&candle.close.to_string());
+            concat.push_str(&format_price(candle.high, digits));
             concat.push(';');
-            concat.push_str(&candle.high.to_string());
+            concat.push_str(&format_price(candle.low, digits));
+            concat.push(';');
+            concat.push_str(&candle.ticks.to_string());
+            concat.push(';');
+            // empty for the candles without volume
+            if let Some(volume) = candle.volume {
+                concat.push_str(&volume.to_string());
+            }
             concat.push(';');
-            concat.push_str(&candle.low.to_string());
+            concat.push_str(&format_price(candle.vwap, digits));
+            concat.push(';');
+            concat.push_str(&candle.open_time.to_string());
+            concat.push(';');
+            concat.push_str(&candle.close_time.to_string());
 
             result.push_str(&concat);
         }
@@ -112,6 +177,17 @@ impl CandleModelEntity {
         partition_key: &str,
         row_key: &str,
         line: &str,
+        rollover: DayRollover,
+    ) -> u64 {
+        let key_date = CandleModelEntity::parse_key_date(candle_type, partition_key, row_key, line);
+        rollover.from_key_date(candle_type, key_date)
+    }
+
+    fn parse_key_date(
+        candle_type: CandleType,
+        partition_key: &str,
+        row_key: &str,
+        line: &str,
     ) -> u64 {
         match candle_type {
             CandleType::Minute => {
@@ -138,6 +214,29 @@ impl CandleModelEntity {
                     .unwrap();
                 return date_time.timestamp() as u64;
             }
+            CandleType::Minute5 | CandleType::Minute15 | CandleType::Minute30 => {
+                let year = partition_key[0..4].parse::<i32>().unwrap();
+                let month = partition_key[4..6].parse::<u32>().unwrap();
+                let day = row_key[0..2].parse::<u32>().unwrap();
+                let hour = line[0..2].parse::<u32>().unwrap();
+                let minute = line[2..4].parse::<u32>().unwrap();
+                let date_time: NaiveDateTime = NaiveDate::from_ymd_opt(year, month, day)
+                    .unwrap()
+                    .and_hms_opt(hour, minute, 0)
+                    .unwrap();
+                date_time.and_utc().timestamp() as u64
+            }
+            CandleType::Hour4 => {
+                let year = partition_key[0..4].parse::<i32>().unwrap();
+                let month = row_key[0..2].parse::<u32>().unwrap();
+                let day = line[0..2].parse::<u32>().unwrap();
+                let hour = line[2..4].parse::<u32>().unwrap();
+                let date_time: NaiveDateTime = NaiveDate::from_ymd_opt(year, month, day)
+                    .unwrap()
+                    .and_hms_opt(hour, 0, 0)
+                    .unwrap();
+                date_time.and_utc().timestamp() as u64
+            }
             CandleType::Day => {
                 let year_d = partition_key[0..4].parse::<i32>().unwrap();
                 let month_d = row_key[0..2].parse::<u32>().unwrap();
@@ -148,6 +247,16 @@ impl CandleModelEntity {
                     .unwrap();
                 return date_time.timestamp() as u64;
             }
+            CandleType::Week => {
+                let year = partition_key[0..4].parse::<i32>().unwrap();
+                let week = line.parse::<u32>().unwrap();
+                NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+                    .timestamp() as u64
+            }
             CandleType::Month => {
                 let year_m = partition_key[0..4].parse::<i32>().unwrap();
                 let month_m = line.parse::<u32>().unwrap();
@@ -165,6 +274,7 @@ impl CandleModelEntity {
         candle_type: CandleType,
         partition_key: &str,
         row_key: &str,
+        rollover: DayRollover,
     ) -> BTreeMap<u64, CandleModel> {
         if src.len() == 0 {
             return BTreeMap::new();
@@ -181,15 +291,35 @@ impl CandleModelEntity {
                 &partition_key,
                 &row_key,
                 sub_items[0],
+                rollover,
             );
+            let close = sub_items[2].parse::<f64>().unwrap();
             result.insert(
                 date_time,
                 CandleModel {
                     datetime: date_time,
                     open: sub_items[1].parse::<f64>().unwrap(),
-                    close: sub_items[1].parse::<f64>().unwrap(),
-                    high: sub_items[1].parse::<f64>().unwrap(),
-                    low: sub_items[1].parse::<f64>().unwrap(),
+                    close,
+                    high: sub_items[3].parse::<f64>().unwrap(),
+                    low: sub_items[4].parse::<f64>().unwrap(),
+                    // candles persisted before ticks and volume were tracked have none of them
+                    ticks: sub_items
+                        .get(5)
+                        .map_or(0, |ticks| ticks.parse::<u64>().unwrap()),
+                    volume: sub_items
+                        .get(6)
+                        .filter(|volume| !volume.is_empty())
+                        .map(|volume| volume.parse::<f64>().unwrap()),
+                    vwap: sub_items
+                        .get(7)
+                        .map_or(close, |vwap| vwap.parse::<f64>().unwrap()),
+                    open_time: sub_items
+                        .get(8)
+                        .map_or(0, |time| time.parse::<u64>().unwrap()),
+                    close_time: sub_items
+                        .get(9)
+                        .map_or(0, |time| time.parse::<u64>().unwrap()),
+                    synthetic: false,
                 },
             );
         }
@@ -197,3 +327,207 @@ impl CandleModelEntity {
         return result;
     }
 }
+
+/// Price rounded to `digits`, as it is when the precision is unknown
+pub fn round_price(price: f64, digits: Option<u32>) -> f64 {
+    match digits {
+        Some(digits) => {
+            let scale = 10f64.powi(digits as i32);
+            (price * scale).round() / scale
+        }
+        None => price,
+    }
+}
+
+pub fn format_price(price: f64, digits: Option<u32>) -> String {
+    round_price(price, digits).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::CandleModelEntity;
+    use crate::models::{CandleModel, CandleType, DayRollover, DayRolloverSettings, DayRollovers};
+
+    #[test]
+    fn test_data_string_roundtrip() {
+        let candle = CandleModel {
+            open: 1.1,
+            close: 1.2,
+            high: 1.3,
+            low: 1.0,
+            datetime: 1662559380,
+            ticks: 3,
+            volume: Some(2.5),
+            vwap: 1.15,
+            open_time: 1662559385,
+            close_time: 1662559430,
+            synthetic: false,
+        };
+
+        let mut entity =
+            CandleModelEntity::create(CandleType::Minute, candle.clone(), DayRollover::UTC);
+        let mut items = BTreeMap::new();
+        items.insert(candle.datetime, candle);
+        entity.set_candles(items, None, CandleType::Minute, DayRollover::UTC);
+
+        let candles = entity.get_candles(CandleType::Minute, DayRollover::UTC);
+        let restored = candles.get(&1662559380).unwrap();
+
+        assert_eq!(restored.open, 1.1);
+        assert_eq!(restored.close, 1.2);
+        assert_eq!(restored.high, 1.3);
+        assert_eq!(restored.low, 1.0);
+        assert_eq!(restored.ticks, 3);
+        assert_eq!(restored.volume, Some(2.5));
+        assert_eq!(restored.vwap, 1.15);
+        assert_eq!(restored.open_time, 1662559385);
+        assert_eq!(restored.close_time, 1662559430);
+    }
+
+    #[test]
+    fn test_legacy_data_string() {
+        let candles = CandleModelEntity::data_string_to_candle_grpc_model(
+            "03;1.1;1.2;1.3;1",
+            CandleType::Minute,
+            "20220907",
+            "14",
+            DayRollover::UTC,
+        );
+        let restored = candles.get(&1662559380).unwrap();
+
+        assert_eq!(restored.close, 1.2);
+        assert_eq!(restored.ticks, 0);
+        assert_eq!(restored.volume, None);
+        assert_eq!(restored.vwap, restored.close);
+    }
+
+    #[test]
+    fn test_keys_roundtrip() {
+        let candle = CandleModel {
+            open: 1.1,
+            close: 1.2,
+            high: 1.3,
+            low: 1.0,
+            datetime: 1662559200,
+            ticks: 1,
+            volume: None,
+            vwap: 1.2,
+            open_time: 1662559200,
+            close_time: 1662559200,
+            synthetic: false,
+        };
+
+        for candle_type in [
+            CandleType::Minute5,
+            CandleType::Minute15,
+            CandleType::Minute30,
+            CandleType::Hour4,
+            CandleType::Week,
+        ] {
+            let datetime = candle_type.format_date_by_type(candle.datetime);
+            let candle = CandleModel {
+                datetime,
+                ..candle.clone()
+            };
+
+            let mut entity =
+                CandleModelEntity::create(candle_type, candle.clone(), DayRollover::UTC);
+            let mut items = BTreeMap::new();
+            items.insert(datetime, candle);
+            entity.set_candles(items, None, candle_type, DayRollover::UTC);
+
+            let candles = entity.get_candles(candle_type, DayRollover::UTC);
+            assert!(candles.contains_key(&datetime), "{:?}", candle_type);
+        }
+    }
+
+    #[test]
+    fn test_week_keys_use_iso_year() {
+        // Monday 2024-12-30 starts the first ISO week of 2025
+        let datetime = 1735516800;
+
+        assert_eq!(
+            CandleModelEntity::generate_partition_key(datetime, CandleType::Week, DayRollover::UTC),
+            "2025"
+        );
+        assert_eq!(
+            CandleModelEntity::generate_row_key(datetime, CandleType::Week, DayRollover::UTC),
+            "01"
+        );
+        assert_eq!(
+            CandleModelEntity::parse_date_time(
+                CandleType::Week,
+                "2025",
+                "01",
+                "01",
+                DayRollover::UTC
+            ),
+            datetime
+        );
+    }
+
+    #[test]
+    fn test_rollover_keys() {
+        let rollover = DayRollovers::new(&[DayRolloverSettings {
+            instruments: vec![],
+            timezone: "America/New_York".to_string(),
+            offset_minutes: -420,
+        }])
+        .get("EURUSD");
+
+        // trading day 2022-09-01 starts on 2022-08-31 at 17:00 EDT
+        let datetime = 1661979600;
+
+        assert_eq!(
+            CandleModelEntity::generate_partition_key(datetime, CandleType::Day, rollover),
+            "2022"
+        );
+        assert_eq!(
+            CandleModelEntity::generate_row_key(datetime, CandleType::Day, rollover),
+            "09"
+        );
+        assert_eq!(
+            CandleModelEntity::to_date_part_string(datetime, CandleType::Day, rollover),
+            "01"
+        );
+        assert_eq!(
+            CandleModelEntity::parse_date_time(CandleType::Day, "2022", "09", "01", rollover),
+            datetime
+        );
+        assert_eq!(
+            CandleModelEntity::parse_date_time(CandleType::Month, "2022", "2022", "09", rollover),
+            datetime
+        );
+    }
+
+    #[test]
+    fn test_data_string_digits() {
+        let candle = CandleModel {
+            open: 1.0999999999999999,
+            close: 1.123456,
+            high: 1.2,
+            low: 1.0,
+            datetime: 1662559380,
+            ticks: 1,
+            volume: Some(0.123456),
+            vwap: 1.11111,
+            open_time: 1662559385,
+            close_time: 1662559385,
+            synthetic: false,
+        };
+
+        let mut items = BTreeMap::new();
+        items.insert(candle.datetime, candle);
+
+        let data =
+            CandleModelEntity::to_data_string(items, Some(3), CandleType::Minute, DayRollover::UTC);
+
+        // volume is not a price and keeps its precision
+        assert_eq!(
+            data,
+            "03;1.1;1.123;1.2;1;1;0.123456;1.111;1662559385;1662559385"
+        );
+    }
+}
@@ -1,23 +1,61 @@
+use chrono::TimeZone;
 use chrono::{DateTime, Datelike, Utc};
-use chrono::{TimeZone};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use service_candle_writer_generated_proto::CandleTypeGrpc;
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, IntoPrimitive, TryFromPrimitive, Hash, Eq, PartialEq)]
+// seconds from Monday 00:00 to the unix epoch (Thursday 00:00)
+const WEEK_OFFSET_SEC: i64 = 3 * 86400;
+
+#[derive(
+    Serialize_repr,
+    Deserialize_repr,
+    Debug,
+    Clone,
+    Copy,
+    IntoPrimitive,
+    TryFromPrimitive,
+    Hash,
+    Eq,
+    PartialEq,
+)]
 #[repr(i32)]
 pub enum CandleType {
     Minute = 0,
     Hour = 1,
     Day = 2,
     Month = 3,
+    Minute5 = 4,
+    Minute15 = 5,
+    Minute30 = 6,
+    Hour4 = 7,
+    Week = 8,
 }
 
 impl CandleType {
+    pub const ALL: [CandleType; 9] = [
+        CandleType::Minute,
+        CandleType::Hour,
+        CandleType::Day,
+        CandleType::Month,
+        CandleType::Minute5,
+        CandleType::Minute15,
+        CandleType::Minute30,
+        CandleType::Hour4,
+        CandleType::Week,
+    ];
+
     pub fn format_date_by_type(&self, date: u64) -> u64 {
         match self {
             CandleType::Minute => date - date % 60,
+            CandleType::Minute5 => date - date % 300,
+            CandleType::Minute15 => date - date % 900,
+            CandleType::Minute30 => date - date % 1800,
+            CandleType::Hour4 => date - date % 14400,
             CandleType::Hour => date - date % 3600,
             CandleType::Day => date - date % 86400,
+            // ISO weeks start on Monday, while the unix epoch is a Thursday
+            CandleType::Week => date - (date + WEEK_OFFSET_SEC as u64) % 604800,
             CandleType::Month => {
                 let date = Utc.timestamp_millis_opt((date * 1000) as i64).unwrap();
                 let start_of_month: DateTime<Utc> = Utc
@@ -29,11 +67,38 @@ impl CandleType {
         }
     }
 
+    /// Start of the period following the one starting at `date`
+    pub fn get_next_date(&self, date: u64) -> u64 {
+        self.format_date_by_type(date + self.get_max_duration_sec())
+    }
+
+    /// Length of the candle period, the longest one for months
+    pub fn get_max_duration_sec(&self) -> u64 {
+        match self {
+            CandleType::Minute => 60,
+            CandleType::Minute5 => 300,
+            CandleType::Minute15 => 900,
+            CandleType::Minute30 => 1800,
+            CandleType::Hour => 3600,
+            CandleType::Hour4 => 14400,
+            CandleType::Day => 86400,
+            CandleType::Week => 604800,
+            CandleType::Month => 31 * 86400,
+        }
+    }
+
     pub fn candle_timestamp_sec(&self, timestamp_sec: i64) -> i64 {
         match self {
             CandleType::Minute => timestamp_sec - timestamp_sec % 60,
+            CandleType::Minute5 => timestamp_sec - timestamp_sec % 300,
+            CandleType::Minute15 => timestamp_sec - timestamp_sec % 900,
+            CandleType::Minute30 => timestamp_sec - timestamp_sec % 1800,
+            CandleType::Hour4 => timestamp_sec - timestamp_sec % 14400,
             CandleType::Hour => timestamp_sec - timestamp_sec % 3600,
             CandleType::Day => timestamp_sec - timestamp_sec % 86400,
+            CandleType::Week => {
+                timestamp_sec - (timestamp_sec + WEEK_OFFSET_SEC).rem_euclid(604800)
+            }
             CandleType::Month => {
                 let date = Utc.timestamp_millis_opt(timestamp_sec * 1000).unwrap();
                 let start_of_month: DateTime<Utc> = Utc
@@ -45,3 +110,52 @@ impl CandleType {
         }
     }
 }
+
+impl From<CandleTypeGrpc> for CandleType {
+    fn from(candle_type: CandleTypeGrpc) -> Self {
+        match candle_type {
+            CandleTypeGrpc::Minute => CandleType::Minute,
+            CandleTypeGrpc::Hour => CandleType::Hour,
+            CandleTypeGrpc::Day => CandleType::Day,
+            CandleTypeGrpc::Month => CandleType::Month,
+            CandleTypeGrpc::Minute5 => CandleType::Minute5,
+            CandleTypeGrpc::Minute15 => CandleType::Minute15,
+            CandleTypeGrpc::Minute30 => CandleType::Minute30,
+            CandleTypeGrpc::Hour4 => CandleType::Hour4,
+            CandleTypeGrpc::Week => CandleType::Week,
+        }
+    }
+}
+
+impl From<CandleType> for CandleTypeGrpc {
+    fn from(candle_type: CandleType) -> Self {
+        match candle_type {
+            CandleType::Minute => CandleTypeGrpc::Minute,
+            CandleType::Hour => CandleTypeGrpc::Hour,
+            CandleType::Day => CandleTypeGrpc::Day,
+            CandleType::Month => CandleTypeGrpc::Month,
+            CandleType::Minute5 => CandleTypeGrpc::Minute5,
+            CandleType::Minute15 => CandleTypeGrpc::Minute15,
+            CandleType::Minute30 => CandleTypeGrpc::Minute30,
+            CandleType::Hour4 => CandleTypeGrpc::Hour4,
+            CandleType::Week => CandleTypeGrpc::Week,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CandleType;
+
+    #[test]
+    fn test_week_starts_on_monday() {
+        // 2022-09-07 14:03:24 UTC, Wednesday
+        assert_eq!(CandleType::Week.format_date_by_type(1662559404), 1662336000);
+        assert_eq!(
+            CandleType::Week.candle_timestamp_sec(1662559404),
+            1662336000
+        );
+        // Monday 00:00 UTC is the start of its own week
+        assert_eq!(CandleType::Week.format_date_by_type(1662336000), 1662336000);
+    }
+}
@@ -0,0 +1,22 @@
+use service_candle_writer_generated_proto::{CandleTypeGrpc, CandleUpdateGrpc, PriceSideGrpc};
+
+use super::{CandleModel, CandleType, PriceSide};
+
+#[derive(Debug, Clone)]
+pub struct CandleUpdate {
+    pub instrument: String,
+    pub side: PriceSide,
+    pub candle_type: CandleType,
+    pub candle: CandleModel,
+}
+
+impl From<CandleUpdate> for CandleUpdateGrpc {
+    fn from(update: CandleUpdate) -> Self {
+        CandleUpdateGrpc {
+            instrument: update.instrument,
+            candle_type: CandleTypeGrpc::from(update.candle_type) as i32,
+            side: PriceSideGrpc::from(update.side) as i32,
+            candle: Some(update.candle.into()),
+        }
+    }
+}
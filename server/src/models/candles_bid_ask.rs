@@ -6,6 +6,8 @@ pub struct CandlesBidAsk{
     pub instrument: String,
     pub bid: f64,
     pub ask: f64,
+    /// None when the feed has no volume
+    pub volume: Option<f64>,
 }
 
 impl From<BidAsk> for CandlesBidAsk {
@@ -15,6 +17,7 @@ impl From<BidAsk> for CandlesBidAsk {
             instrument: bid_ask.id,
             bid: bid_ask.bid,
             ask: bid_ask.ask,
+            volume: bid_ask.has_volume.then_some(bid_ask.volume),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+use service_candle_writer_generated_proto::Trade;
+
+#[derive(Debug, Clone)]
+pub struct CandlesTrade {
+    pub date: u64,
+    pub instrument: String,
+    pub price: f64,
+    pub volume: f64,
+}
+
+impl From<Trade> for CandlesTrade {
+    fn from(trade: Trade) -> Self {
+        CandlesTrade {
+            date: trade.unix_time_sec,
+            instrument: trade.instrument,
+            price: trade.price,
+            volume: trade.volume,
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, LocalResult, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use super::{CandleType, MAX_KEY_DATE};
+
+/// Daily rollover of an instrument group, configured in the settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DayRolloverSettings {
+    /// Instruments of the group, the rollover of all other instruments when empty
+    #[serde(rename = "Instruments", default)]
+    pub instruments: Vec<String>,
+
+    /// IANA timezone name, e.g. "America/New_York"
+    #[serde(rename = "Timezone")]
+    pub timezone: String,
+
+    /// Start of the trading day relative to the local midnight, -420 rolls at 17:00 of the previous day
+    #[serde(rename = "OffsetMinutes", default)]
+    pub offset_minutes: i64,
+}
+
+/// Where the Day, Week and Month candles of an instrument start.
+/// Intraday candles are always aligned to UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayRollover {
+    timezone: Tz,
+    offset_sec: i64,
+}
+
+impl DayRollover {
+    pub const UTC: DayRollover = DayRollover {
+        timezone: Tz::UTC,
+        offset_sec: 0,
+    };
+
+    pub fn new(timezone: Tz, offset_minutes: i64) -> Self {
+        Self {
+            timezone,
+            offset_sec: offset_minutes * 60,
+        }
+    }
+
+    fn is_daily(candle_type: CandleType) -> bool {
+        matches!(
+            candle_type,
+            CandleType::Day | CandleType::Week | CandleType::Month
+        )
+    }
+
+    /// Trading date of the day period the timestamp belongs to,
+    /// dates beyond the storage keys are clamped to the last one
+    pub fn get_trading_date(&self, date: u64) -> NaiveDate {
+        let local = self
+            .timezone
+            .timestamp_opt(u64::min(date, MAX_KEY_DATE) as i64, 0)
+            .unwrap()
+            .naive_local();
+
+        (local - Duration::seconds(self.offset_sec)).date()
+    }
+
+    /// Start of the trading date as a unix timestamp
+    pub fn get_day_start(&self, date: NaiveDate) -> u64 {
+        let local = date.and_hms_opt(0, 0, 0).unwrap() + Duration::seconds(self.offset_sec);
+
+        let start = match self.timezone.from_local_datetime(&local) {
+            LocalResult::Single(start) => start,
+            LocalResult::Ambiguous(earliest, _) => earliest,
+            // the rollover falls into a DST gap, the day starts once the clocks are moved
+            LocalResult::None => self
+                .timezone
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+                .unwrap(),
+        };
+
+        start.timestamp() as u64
+    }
+
+    fn get_period_date(candle_type: CandleType, trading_date: NaiveDate) -> NaiveDate {
+        match candle_type {
+            CandleType::Week => {
+                trading_date - Duration::days(trading_date.weekday().num_days_from_monday() as i64)
+            }
+            CandleType::Month => trading_date.with_day(1).unwrap(),
+            _ => trading_date,
+        }
+    }
+
+    /// Start of the candle period the timestamp belongs to
+    pub fn format_date(&self, candle_type: CandleType, date: u64) -> u64 {
+        if !Self::is_daily(candle_type) {
+            return candle_type.format_date_by_type(date);
+        }
+
+        let trading_date = self.get_trading_date(date);
+        self.get_day_start(Self::get_period_date(candle_type, trading_date))
+    }
+
+    /// Start of the period following the one starting at `date`
+    pub fn get_next_date(&self, candle_type: CandleType, date: u64) -> u64 {
+        if !Self::is_daily(candle_type) {
+            return candle_type.get_next_date(date);
+        }
+
+        let period_date = Self::get_period_date(candle_type, self.get_trading_date(date));
+        let next_date = match candle_type {
+            CandleType::Week => period_date + Duration::days(7),
+            CandleType::Month => period_date
+                .checked_add_months(chrono::Months::new(1))
+                .unwrap(),
+            _ => period_date + Duration::days(1),
+        };
+
+        self.get_day_start(next_date)
+    }
+
+    /// Date the storage keys are built from, the trading date at 00:00 UTC for the daily candles
+    pub fn to_key_date(&self, candle_type: CandleType, datetime: u64) -> u64 {
+        if !Self::is_daily(candle_type) {
+            return datetime;
+        }
+
+        self.get_trading_date(datetime)
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64
+    }
+
+    /// Reverse of `to_key_date`
+    pub fn from_key_date(&self, candle_type: CandleType, key_date: u64) -> u64 {
+        if !Self::is_daily(candle_type) {
+            return key_date;
+        }
+
+        self.get_day_start(DayRollover::UTC.get_trading_date(key_date))
+    }
+}
+
+impl Default for DayRollover {
+    fn default() -> Self {
+        DayRollover::UTC
+    }
+}
+
+/// Rollovers of every instrument
+#[derive(Debug, Clone, Default)]
+pub struct DayRollovers {
+    default: DayRollover,
+    by_instrument: HashMap<String, DayRollover>,
+}
+
+impl DayRollovers {
+    pub fn new(settings: &[DayRolloverSettings]) -> Self {
+        let mut result = DayRollovers::default();
+
+        for group in settings {
+            let timezone: Tz = group
+                .timezone
+                .parse()
+                .unwrap_or_else(|err| panic!("Invalid timezone {}: {:?}", group.timezone, err));
+            let rollover = DayRollover::new(timezone, group.offset_minutes);
+
+            if group.instruments.is_empty() {
+                result.default = rollover;
+            }
+
+            for instrument in group.instruments.iter() {
+                result.by_instrument.insert(instrument.clone(), rollover);
+            }
+        }
+
+        result
+    }
+
+    pub fn get(&self, instrument: &str) -> DayRollover {
+        self.by_instrument
+            .get(instrument)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DayRollover, DayRolloverSettings, DayRollovers};
+    use crate::models::{CandleType, MAX_KEY_DATE};
+
+    fn new_york() -> DayRollovers {
+        DayRollovers::new(&[DayRolloverSettings {
+            instruments: vec!["EURUSD".to_string()],
+            timezone: "America/New_York".to_string(),
+            offset_minutes: -420,
+        }])
+    }
+
+    #[test]
+    fn test_new_york_rollover() {
+        let rollovers = new_york();
+        let rollover = rollovers.get("EURUSD");
+
+        // 2022-09-07 14:03:24 UTC is 10:03 EDT, the day started on 2022-09-06 at 17:00 EDT
+        assert_eq!(
+            rollover.format_date(CandleType::Day, 1662559404),
+            1662498000
+        );
+        // 2022-09-07 21:30:00 UTC is 17:30 EDT, already the next trading day
+        assert_eq!(
+            rollover.format_date(CandleType::Day, 1662586200),
+            1662584400
+        );
+        assert_eq!(
+            rollover.get_next_date(CandleType::Day, 1662498000),
+            1662584400
+        );
+        // the week of 2022-09-05 started on Sunday 2022-09-04 at 17:00 EDT
+        assert_eq!(
+            rollover.format_date(CandleType::Week, 1662559404),
+            1662325200
+        );
+        // September 2022 started on 2022-08-31 at 17:00 EDT
+        assert_eq!(
+            rollover.format_date(CandleType::Month, 1662559404),
+            1661979600
+        );
+        // intraday candles stay in UTC
+        assert_eq!(
+            rollover.format_date(CandleType::Hour, 1662559404),
+            1662559200
+        );
+
+        assert_eq!(rollovers.get("BTCUSD"), DayRollover::UTC);
+        assert_eq!(
+            DayRollover::UTC.format_date(CandleType::Day, 1662559404),
+            1662508800
+        );
+    }
+
+    #[test]
+    fn test_rollover_across_dst() {
+        let rollover = new_york().get("EURUSD");
+
+        // the trading day of 2022-11-06 starts at 17:00 EDT and ends at 17:00 EST, 25 hours later
+        assert_eq!(
+            rollover.get_next_date(CandleType::Day, 1667595600),
+            1667682000
+        );
+        assert_eq!(
+            rollover.get_next_date(CandleType::Day, 1667682000),
+            1667772000
+        );
+    }
+
+    #[test]
+    fn test_key_date_roundtrip() {
+        let rollover = new_york().get("EURUSD");
+
+        let datetime = rollover.format_date(CandleType::Day, 1662559404);
+        let key_date = rollover.to_key_date(CandleType::Day, datetime);
+
+        // trading date 2022-09-07
+        assert_eq!(key_date, 1662508800);
+        assert_eq!(rollover.from_key_date(CandleType::Day, key_date), datetime);
+    }
+    #[test]
+    fn test_dates_beyond_storage_keys() {
+        let rollover = DayRollover::UTC;
+        let last_day = rollover.format_date(CandleType::Day, MAX_KEY_DATE);
+
+        // 9999-12-31
+        assert_eq!(last_day, 253402214400);
+        assert_eq!(rollover.format_date(CandleType::Day, u64::MAX), last_day);
+        assert_eq!(
+            rollover.format_date(CandleType::Month, 8_300_000_000_000),
+            rollover.format_date(CandleType::Month, MAX_KEY_DATE)
+        );
+    }
+}
@@ -1,9 +1,27 @@
 mod candle_type;
 mod candle;
 mod candles_bid_ask;
+mod candles_trade;
 mod candle_model_entity;
+mod candle_update;
+mod day_rollover;
+mod price_side;
+mod session_calendar;
+mod spread_candle;
+mod spread_candle_entity;
+mod tick_rejection;
+mod timeframe;
 
 pub use candle_type::*;
 pub use candle::*;
 pub use candles_bid_ask::*;
-pub use candle_model_entity::*;
\ No newline at end of file
+pub use candles_trade::*;
+pub use candle_model_entity::*;
+pub use candle_update::*;
+pub use day_rollover::*;
+pub use price_side::*;
+pub use session_calendar::*;
+pub use spread_candle::*;
+pub use spread_candle_entity::*;
+pub use tick_rejection::*;
+pub use timeframe::*;
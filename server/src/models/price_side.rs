@@ -0,0 +1,69 @@
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use service_candle_writer_generated_proto::PriceSideGrpc;
+
+use super::CandlesBidAsk;
+
+#[derive(
+    Serialize_repr,
+    Deserialize_repr,
+    Debug,
+    Clone,
+    Copy,
+    IntoPrimitive,
+    TryFromPrimitive,
+    Hash,
+    Eq,
+    PartialEq,
+)]
+#[repr(i32)]
+pub enum PriceSide {
+    Bid = 0,
+    Ask = 1,
+    Mid = 2,
+    Trade = 3,
+}
+
+impl PriceSide {
+    pub const ALL: [PriceSide; 4] = [
+        PriceSide::Bid,
+        PriceSide::Ask,
+        PriceSide::Mid,
+        PriceSide::Trade,
+    ];
+
+    /// Sides built from the bid/ask quotes
+    pub const QUOTES: [PriceSide; 3] = [PriceSide::Bid, PriceSide::Ask, PriceSide::Mid];
+
+    /// Rate of the side in the quote, trade candles are built from trades instead
+    pub fn get_rate(&self, bid_ask: &CandlesBidAsk) -> Option<f64> {
+        match self {
+            PriceSide::Bid => Some(bid_ask.bid),
+            PriceSide::Ask => Some(bid_ask.ask),
+            PriceSide::Mid => Some((bid_ask.bid + bid_ask.ask) / 2.0),
+            PriceSide::Trade => None,
+        }
+    }
+}
+
+impl From<PriceSideGrpc> for PriceSide {
+    fn from(side: PriceSideGrpc) -> Self {
+        match side {
+            PriceSideGrpc::Bid => PriceSide::Bid,
+            PriceSideGrpc::Ask => PriceSide::Ask,
+            PriceSideGrpc::Mid => PriceSide::Mid,
+            PriceSideGrpc::Trade => PriceSide::Trade,
+        }
+    }
+}
+
+impl From<PriceSide> for PriceSideGrpc {
+    fn from(side: PriceSide) -> Self {
+        match side {
+            PriceSide::Bid => PriceSideGrpc::Bid,
+            PriceSide::Ask => PriceSideGrpc::Ask,
+            PriceSide::Mid => PriceSideGrpc::Mid,
+            PriceSide::Trade => PriceSideGrpc::Trade,
+        }
+    }
+}
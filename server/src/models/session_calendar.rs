@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const MINUTES_IN_DAY: u32 = 24 * 60;
+
+/// Trading hours of an instrument group, configured in the settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionCalendarSettings {
+    /// Instruments of the group, the calendar of all other instruments when empty
+    #[serde(rename = "Instruments", default)]
+    pub instruments: Vec<String>,
+
+    /// IANA timezone name the sessions and holidays are defined in
+    #[serde(rename = "Timezone")]
+    pub timezone: String,
+
+    #[serde(rename = "Sessions")]
+    pub sessions: Vec<TradingSessionSettings>,
+
+    /// Closed dates as "YYYY-MM-DD"
+    #[serde(rename = "Holidays", default)]
+    pub holidays: Vec<String>,
+
+    /// Drop the quotes outside the sessions, otherwise they are aggregated and only counted
+    #[serde(rename = "DropOutOfSessionTicks", default)]
+    pub drop_out_of_session_ticks: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradingSessionSettings {
+    /// Week days the session opens on, e.g. "Mon"
+    #[serde(rename = "Days")]
+    pub days: Vec<String>,
+
+    /// Local open time as "HH:MM"
+    #[serde(rename = "Open")]
+    pub open: String,
+
+    /// Local close time as "HH:MM", a close before the open ends the session on the next day
+    #[serde(rename = "Close")]
+    pub close: String,
+}
+
+#[derive(Debug, Clone)]
+struct TradingSession {
+    days: Vec<Weekday>,
+    open_minute: u32,
+    close_minute: u32,
+}
+
+impl TradingSession {
+    fn is_open(&self, weekday: Weekday, minute: u32) -> bool {
+        if self.open_minute < self.close_minute {
+            return self.days.contains(&weekday)
+                && minute >= self.open_minute
+                && minute < self.close_minute;
+        }
+
+        (self.days.contains(&weekday) && minute >= self.open_minute)
+            || (self.days.contains(&weekday.pred()) && minute < self.close_minute)
+    }
+}
+
+/// Open hours and holidays of an instrument group
+#[derive(Debug, Clone)]
+pub struct SessionCalendar {
+    timezone: Tz,
+    sessions: Vec<TradingSession>,
+    holidays: HashSet<NaiveDate>,
+    pub drop_out_of_session_ticks: bool,
+}
+
+impl SessionCalendar {
+    pub fn new(settings: &SessionCalendarSettings) -> Result<Self, String> {
+        let timezone: Tz = settings
+            .timezone
+            .parse()
+            .map_err(|err| format!("Invalid timezone {}: {:?}", settings.timezone, err))?;
+
+        let mut sessions = Vec::with_capacity(settings.sessions.len());
+        for session in settings.sessions.iter() {
+            let days = session
+                .days
+                .iter()
+                .map(|day| {
+                    day.parse::<Weekday>()
+                        .map_err(|_| format!("Invalid week day: {}", day))
+                })
+                .collect::<Result<Vec<Weekday>, String>>()?;
+
+            sessions.push(TradingSession {
+                days,
+                open_minute: parse_minute(&session.open)?,
+                close_minute: parse_minute(&session.close)?,
+            });
+        }
+
+        let holidays = settings
+            .holidays
+            .iter()
+            .map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid holiday: {}", date))
+            })
+            .collect::<Result<HashSet<NaiveDate>, String>>()?;
+
+        Ok(Self {
+            timezone,
+            sessions,
+            holidays,
+            drop_out_of_session_ticks: settings.drop_out_of_session_ticks,
+        })
+    }
+
+    fn get_local(&self, date: u64) -> NaiveDateTime {
+        self.timezone
+            .timestamp_opt(date as i64, 0)
+            .unwrap()
+            .naive_local()
+    }
+
+    pub fn is_open(&self, date: u64) -> bool {
+        let local = self.get_local(date);
+
+        if self.holidays.contains(&local.date()) {
+            return false;
+        }
+
+        let minute = local.hour() * 60 + local.minute();
+        self.sessions
+            .iter()
+            .any(|session| session.is_open(local.weekday(), minute))
+    }
+
+    /// Whether any moment of the [date_from, date_to) range is in a session
+    pub fn has_session_between(&self, date_from: u64, date_to: u64) -> bool {
+        let mut date = date_from;
+
+        while date < date_to {
+            if self.is_open(date) {
+                return true;
+            }
+
+            let next_date = self.get_next_boundary(date);
+
+            // a daylight saving shift in between moves the local boundaries,
+            // it is passed minute by minute
+            let local_step = (self.get_local(next_date) - self.get_local(date)).num_seconds();
+            date = match local_step == (next_date - date) as i64 {
+                true => next_date,
+                false => (date / 60 + 1) * 60,
+            };
+        }
+
+        false
+    }
+
+    /// The open state only changes at a session open or close and at the local midnight
+    fn get_next_boundary(&self, date: u64) -> u64 {
+        let local = self.get_local(date);
+        let minute = local.hour() * 60 + local.minute();
+
+        let next_minute = self
+            .sessions
+            .iter()
+            .flat_map(|session| [session.open_minute, session.close_minute])
+            .filter(|boundary| *boundary > minute)
+            .fold(MINUTES_IN_DAY, u32::min);
+
+        date + ((next_minute - minute) * 60 - local.second()) as u64
+    }
+}
+
+fn parse_minute(time: &str) -> Result<u32, String> {
+    let invalid = || format!("Invalid session time: {}", time);
+
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.parse().map_err(|_| invalid())?;
+
+    let result = hour * 60 + minute;
+    if minute >= 60 || result > MINUTES_IN_DAY {
+        return Err(invalid());
+    }
+
+    Ok(result)
+}
+
+/// Session calendars of every instrument, instruments without one trade around the clock
+#[derive(Debug, Clone, Default)]
+pub struct SessionCalendars {
+    default: Option<SessionCalendar>,
+    by_instrument: HashMap<String, SessionCalendar>,
+}
+
+impl SessionCalendars {
+    pub fn new(settings: &[SessionCalendarSettings]) -> Self {
+        let mut result = SessionCalendars::default();
+
+        for group in settings {
+            let calendar = SessionCalendar::new(group).unwrap_or_else(|err| panic!("{}", err));
+
+            if group.instruments.is_empty() {
+                result.default = Some(calendar.clone());
+            }
+
+            for instrument in group.instruments.iter() {
+                result
+                    .by_instrument
+                    .insert(instrument.clone(), calendar.clone());
+            }
+        }
+
+        result
+    }
+
+    pub fn get(&self, instrument: &str) -> Option<&SessionCalendar> {
+        self.by_instrument.get(instrument).or(self.default.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionCalendarSettings, SessionCalendars, TradingSessionSettings};
+
+    #[test]
+    fn test_session_calendar() {
+        let calendars = SessionCalendars::new(&[
+            SessionCalendarSettings {
+                instruments: vec!["AAPL".to_string()],
+                timezone: "America/New_York".to_string(),
+                sessions: vec![TradingSessionSettings {
+                    days: ["Mon", "Tue", "Wed", "Thu", "Fri"]
+                        .iter()
+                        .map(|day| day.to_string())
+                        .collect(),
+                    open: "09:30".to_string(),
+                    close: "16:00".to_string(),
+                }],
+                holidays: vec!["2022-09-05".to_string()],
+                drop_out_of_session_ticks: true,
+            },
+            SessionCalendarSettings {
+                instruments: vec!["EURUSD".to_string()],
+                timezone: "America/New_York".to_string(),
+                sessions: vec![TradingSessionSettings {
+                    days: ["Sun", "Mon", "Tue", "Wed", "Thu"]
+                        .iter()
+                        .map(|day| day.to_string())
+                        .collect(),
+                    open: "17:00".to_string(),
+                    close: "17:00".to_string(),
+                }],
+                holidays: vec![],
+                drop_out_of_session_ticks: false,
+            },
+        ]);
+
+        let stock = calendars.get("AAPL").unwrap();
+        // Wednesday 2022-09-07 10:03 EDT
+        assert!(stock.is_open(1662559404));
+        // Wednesday 2022-09-07 17:30 EDT
+        assert!(!stock.is_open(1662586200));
+        // Labor Day, Monday 2022-09-05 10:03 EDT
+        assert!(!stock.is_open(1662386604));
+        // the whole Saturday 2022-09-10
+        assert!(!stock.has_session_between(1662782400, 1662868800));
+        // Saturday 2022-09-10 00:00 EDT till Monday 2022-09-12 09:30 EDT and a second later
+        assert!(!stock.has_session_between(1662782400, 1662989400));
+        assert!(stock.has_session_between(1662782400, 1662989401));
+        // Labor Day week, the session of Tuesday 2022-09-06 is found
+        assert!(stock.has_session_between(1662350400, 1662955200));
+
+        let fx = calendars.get("EURUSD").unwrap();
+        // Friday 2022-09-09 16:59 EDT and 17:00 EDT
+        assert!(fx.is_open(1662757140));
+        assert!(!fx.is_open(1662757200));
+        // Sunday 2022-09-11 17:00 EDT
+        assert!(fx.is_open(1662930000));
+        // Saturday 2022-11-05 00:00 EDT till Sunday 2022-11-06 17:00 EST, over the end of the DST
+        assert!(!fx.has_session_between(1667620800, 1667772000));
+        assert!(fx.has_session_between(1667620800, 1667772060));
+
+        assert!(calendars.get("BTCUSD").is_none());
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use service_candle_writer_generated_proto::SpreadCandleGrpcModel;
+
+/// Spread (ask - bid) statistics of a candle period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadCandleModel {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub close: f64,
+    pub ticks: u64,
+    pub datetime: u64,
+    /// Timestamp of the latest spread, the close comes from it. Not persisted,
+    /// restored candles start from the beginning of their period.
+    #[serde(default)]
+    pub close_time: u64,
+}
+
+impl SpreadCandleModel {
+    /// Spread candle of the period starting at `datetime` opened by the tick at `date`
+    pub fn new_from_spread(datetime: u64, date: u64, spread: f64) -> Self {
+        Self {
+            min: spread,
+            max: spread,
+            avg: spread,
+            close: spread,
+            ticks: 1,
+            datetime,
+            close_time: date,
+        }
+    }
+
+    /// Ticks may arrive out of order, the close follows the tick timestamps
+    pub fn update_by_spread(&mut self, spread: f64, date: u64) {
+        if date >= self.close_time {
+            self.close = spread;
+            self.close_time = date;
+        }
+
+        self.ticks += 1;
+        self.avg += (spread - self.avg) / self.ticks as f64;
+
+        if self.max < spread {
+            self.max = spread;
+        }
+
+        if self.min > spread {
+            self.min = spread;
+        }
+    }
+
+    /// Combines the statistics of the same period of another name of the instrument
+    pub fn merge(&self, other: &SpreadCandleModel) -> SpreadCandleModel {
+        let (close, close_time) = match other.close_time > self.close_time {
+            true => (other.close, other.close_time),
+            false => (self.close, self.close_time),
+        };
+        let ticks = self.ticks + other.ticks;
+        let avg = match ticks {
+            0 => close,
+            _ => (self.avg * self.ticks as f64 + other.avg * other.ticks as f64) / ticks as f64,
+        };
+
+        SpreadCandleModel {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            avg,
+            close,
+            ticks,
+            datetime: self.datetime,
+            close_time,
+        }
+    }
+}
+
+impl From<SpreadCandleModel> for SpreadCandleGrpcModel {
+    fn from(candle: SpreadCandleModel) -> Self {
+        SpreadCandleGrpcModel {
+            datetime: candle.datetime,
+            min: candle.min,
+            max: candle.max,
+            avg: candle.avg,
+            close: candle.close,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpreadCandleModel;
+
+    #[test]
+    fn test_spread_statistics() {
+        let mut candle = SpreadCandleModel::new_from_spread(1662559380, 1662559381, 0.2);
+        candle.update_by_spread(0.4, 1662559382);
+        candle.update_by_spread(0.1, 1662559384);
+        candle.update_by_spread(0.3, 1662559383);
+
+        assert_eq!(candle.datetime, 1662559380);
+        assert_eq!(candle.min, 0.1);
+        assert_eq!(candle.max, 0.4);
+        assert_eq!(candle.close, 0.1);
+        assert_eq!(candle.close_time, 1662559384);
+        assert_eq!(candle.ticks, 4);
+        assert!((candle.avg - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merge_spreads() {
+        let mut candle = SpreadCandleModel::new_from_spread(1662559380, 1662559381, 0.2);
+        candle.update_by_spread(0.4, 1662559390);
+        let other = SpreadCandleModel::new_from_spread(1662559380, 1662559385, 0.6);
+
+        let merged = candle.merge(&other);
+        assert_eq!(merged.datetime, 1662559380);
+        assert_eq!(merged.min, 0.2);
+        assert_eq!(merged.max, 0.6);
+        assert_eq!(merged.close, 0.4);
+        assert_eq!(merged.ticks, 3);
+        assert!((merged.avg - 0.4).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{CandleModelEntity, CandleType, DayRollover, SpreadCandleModel};
+
+/// Spread candles are grouped into entities with the same keys as `CandleModelEntity`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadCandleEntity {
+    #[serde(rename = "PartitionKey")]
+    pub partition_key: String,
+    #[serde(rename = "RowKey")]
+    pub row_key: String,
+
+    #[serde(rename = "Data")]
+    pub data: String,
+}
+
+impl SpreadCandleEntity {
+    pub fn create(candle_type: CandleType, datetime: u64, rollover: DayRollover) -> Self {
+        Self {
+            partition_key: CandleModelEntity::generate_partition_key(
+                datetime,
+                candle_type,
+                rollover,
+            ),
+            row_key: CandleModelEntity::generate_row_key(datetime, candle_type, rollover),
+            data: "".to_string(),
+        }
+    }
+
+    pub fn get_candles(
+        &self,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) -> BTreeMap<u64, SpreadCandleModel> {
+        let mut result = BTreeMap::new();
+
+        if self.data.is_empty() {
+            return result;
+        }
+
+        for line in self.data.split('|') {
+            let sub_items = line.split(';').collect::<Vec<&str>>();
+
+            let datetime = CandleModelEntity::parse_date_time(
+                candle_type,
+                &self.partition_key,
+                &self.row_key,
+                sub_items[0],
+                rollover,
+            );
+
+            result.insert(
+                datetime,
+                SpreadCandleModel {
+                    datetime,
+                    min: sub_items[1].parse::<f64>().unwrap(),
+                    max: sub_items[2].parse::<f64>().unwrap(),
+                    avg: sub_items[3].parse::<f64>().unwrap(),
+                    close: sub_items[4].parse::<f64>().unwrap(),
+                    ticks: sub_items[5].parse::<u64>().unwrap(),
+                    close_time: datetime,
+                },
+            );
+        }
+
+        result
+    }
+
+    pub fn set_candles(
+        &mut self,
+        items: BTreeMap<u64, SpreadCandleModel>,
+        candle_type: CandleType,
+        rollover: DayRollover,
+    ) {
+        let mut result = String::with_capacity(20);
+
+        for (datetime, candle) in items.into_iter() {
+            if !result.is_empty() {
+                result.push('|');
+            }
+
+            result.push_str(&format!(
+                "{};{};{};{};{};{}",
+                CandleModelEntity::to_date_part_string(datetime, candle_type, rollover),
+                candle.min,
+                candle.max,
+                candle.avg,
+                candle.close,
+                candle.ticks,
+            ));
+        }
+
+        self.data = result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::SpreadCandleEntity;
+    use crate::models::{CandleType, DayRollover, SpreadCandleModel};
+
+    #[test]
+    fn test_data_string_roundtrip() {
+        let mut candle = SpreadCandleModel::new_from_spread(1662559200, 1662559201, 0.5);
+        candle.update_by_spread(0.3, 1662559202);
+
+        let mut entity =
+            SpreadCandleEntity::create(CandleType::Hour, candle.datetime, DayRollover::UTC);
+        let mut items = BTreeMap::new();
+        items.insert(candle.datetime, candle);
+        entity.set_candles(items, CandleType::Hour, DayRollover::UTC);
+
+        let candles = entity.get_candles(CandleType::Hour, DayRollover::UTC);
+        let restored = candles.get(&1662559200).unwrap();
+
+        assert_eq!(restored.min, 0.3);
+        assert_eq!(restored.max, 0.5);
+        assert_eq!(restored.avg, 0.4);
+        assert_eq!(restored.close, 0.3);
+        assert_eq!(restored.ticks, 2);
+    }
+}
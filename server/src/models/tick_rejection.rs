@@ -0,0 +1,26 @@
+/// Reason the tick validation rejected a quote
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum TickRejection {
+    /// NaN, zero or negative price
+    InvalidPrice,
+    /// Bid above ask
+    CrossedQuote,
+    /// Too far from the recent price of the instrument
+    Spike,
+    /// Outside the trading session of the instrument
+    OutOfSession,
+    /// Not an enabled instrument of the instrument dictionary
+    UnknownInstrument,
+}
+
+impl TickRejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TickRejection::InvalidPrice => "invalid_price",
+            TickRejection::CrossedQuote => "crossed_quote",
+            TickRejection::Spike => "spike",
+            TickRejection::OutOfSession => "out_of_session",
+            TickRejection::UnknownInstrument => "unknown_instrument",
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use super::{CandleType, DayRollover};
+
+/// Candle type aggregated by the service, configured in the settings
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "TimeframeSettings")]
+pub struct Timeframe {
+    #[serde(rename = "CandleType")]
+    pub candle_type: CandleType,
+
+    /// Amount of candles kept in memory, all of them when not set.
+    /// Required for the intraday candle types.
+    #[serde(rename = "Limit")]
+    pub limit: Option<usize>,
+
+    #[serde(rename = "Persist")]
+    pub persist: bool,
+}
+
+#[derive(Deserialize)]
+struct TimeframeSettings {
+    #[serde(rename = "CandleType")]
+    candle_type: CandleType,
+    #[serde(rename = "Limit", default)]
+    limit: Option<usize>,
+    #[serde(rename = "Persist", default = "default_persist")]
+    persist: bool,
+}
+
+fn default_persist() -> bool {
+    true
+}
+
+impl TryFrom<TimeframeSettings> for Timeframe {
+    type Error = String;
+
+    // unlimited intraday candles would restore the whole history since 1970 partition by partition
+    fn try_from(settings: TimeframeSettings) -> Result<Self, Self::Error> {
+        let is_intraday = !matches!(
+            settings.candle_type,
+            CandleType::Day | CandleType::Week | CandleType::Month
+        );
+
+        if is_intraday && settings.limit.is_none() {
+            return Err(format!(
+                "Limit is required for the intraday timeframe {:?}",
+                settings.candle_type
+            ));
+        }
+
+        Ok(Self {
+            candle_type: settings.candle_type,
+            limit: settings.limit,
+            persist: settings.persist,
+        })
+    }
+}
+
+impl Timeframe {
+    pub fn new(candle_type: CandleType, limit: Option<usize>) -> Self {
+        Self {
+            candle_type,
+            limit,
+            persist: true,
+        }
+    }
+
+    /// Oldest candle date kept in memory for this timeframe
+    pub fn get_limit_date(&self, now: u64, rollover: DayRollover) -> u64 {
+        match self.limit {
+            Some(limit) => {
+                let period = self.candle_type.get_max_duration_sec() * limit as u64;
+                rollover.format_date(self.candle_type, now.saturating_sub(period))
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timeframe;
+    use crate::models::{CandleType, DayRollover};
+
+    #[test]
+    fn test_timeframe_settings() {
+        let timeframes: Vec<Timeframe> = serde_json::from_str(
+            r#"[{"CandleType":0,"Limit":100},{"CandleType":2,"Persist":false}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(timeframes[0].candle_type, CandleType::Minute);
+        assert_eq!(timeframes[0].limit, Some(100));
+        assert!(timeframes[0].persist);
+        assert_eq!(timeframes[1].candle_type, CandleType::Day);
+        assert_eq!(timeframes[1].limit, None);
+        assert!(!timeframes[1].persist);
+
+        assert_eq!(
+            timeframes[0].get_limit_date(1662559404, DayRollover::UTC),
+            1662553380
+        );
+        assert_eq!(
+            timeframes[1].get_limit_date(1662559404, DayRollover::UTC),
+            0
+        );
+
+        assert!(serde_json::from_str::<Timeframe>(r#"{"CandleType":1}"#).is_err());
+    }
+}
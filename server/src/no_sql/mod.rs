@@ -1,5 +1,6 @@
 pub mod block_reader_record;
 pub mod nft_registry;
+pub mod spot_instrument;
 
 /* use block_reader_record::BlockReaderNoSql;
 use nft_registry::TABLE_NAME;
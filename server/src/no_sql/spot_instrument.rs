@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+pub const TABLE_NAME: &str = "spot-instruments";
+
+/// Instrument of the spot-instruments dictionary, the row key is the instrument symbol
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpotInstrumentNoSqlEntity {
+    #[serde(rename = "PartitionKey")]
+    pub partition_key: String,
+    #[serde(rename = "RowKey")]
+    pub row_key: String,
+    #[serde(rename = "TimeStamp", default)]
+    pub time_stamp: String,
+    #[serde(rename = "BaseAsset", default)]
+    pub base_asset: String,
+    #[serde(rename = "QuoteAsset", default)]
+    pub quote_asset: String,
+    /// Price digits of the instrument
+    #[serde(rename = "Accuracy")]
+    pub accuracy: u32,
+    #[serde(rename = "IsEnabled", default)]
+    pub is_enabled: bool,
+}
+
+impl my_no_sql_server_abstractions::MyNoSqlEntity for SpotInstrumentNoSqlEntity {
+    fn get_partition_key(&self) -> &str {
+        &self.partition_key[..]
+    }
+    fn get_row_key(&self) -> &str {
+        &self.row_key[..]
+    }
+    fn get_time_stamp(&self) -> i64 {
+        0
+    }
+
+    const TABLE_NAME: &'static str = TABLE_NAME;
+}
@@ -0,0 +1,402 @@
+use std::{collections::HashSet, sync::Arc};
+
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::instrument;
+
+use crate::caches::{CandlesInstrumentsCache, SpreadsCache};
+use crate::domain::{
+    fill_candles_gaps, get_candles_history, get_spread_history, CandlesStorage, InstrumentStorage,
+    SpreadsStorage,
+};
+use crate::models::{CandleType, CandleUpdate, PriceSide, SessionCalendars};
+use service_candle_writer_generated_proto::candles_grpc::candles_service_server::CandlesService;
+use service_candle_writer_generated_proto::candles_grpc::{
+    CandleTypeGrpc, CandleUpdateGrpc, GetCandlesRequest, GetCandlesResponse, GetLastCandlesRequest,
+    GetLastCandlesResponse, GetSpreadCandlesRequest, GetSpreadCandlesResponse, InstrumentGrpcModel,
+    InstrumentLastCandlesGrpc, LastCandleGrpc, LastPriceGrpc, ListInstrumentsRequest,
+    ListInstrumentsResponse, PriceSideGrpc, SubscribeCandlesRequest,
+};
+
+const SUBSCRIPTION_BUFFER: usize = 1024;
+
+pub struct CandlesServiceImpl {
+    cache: Arc<CandlesInstrumentsCache>,
+    candles_storage: Arc<dyn CandlesStorage>,
+    instrument_storage: Arc<InstrumentStorage>,
+    candle_updates: broadcast::Sender<CandleUpdate>,
+    spreads_cache: Arc<SpreadsCache>,
+    spreads_storage: Arc<dyn SpreadsStorage>,
+    session_calendars: Arc<SessionCalendars>,
+}
+
+impl CandlesServiceImpl {
+    pub fn new(
+        cache: Arc<CandlesInstrumentsCache>,
+        candles_storage: Arc<dyn CandlesStorage>,
+        instrument_storage: Arc<InstrumentStorage>,
+        candle_updates: broadcast::Sender<CandleUpdate>,
+        spreads_cache: Arc<SpreadsCache>,
+        spreads_storage: Arc<dyn SpreadsStorage>,
+        session_calendars: Arc<SessionCalendars>,
+    ) -> Self {
+        CandlesServiceImpl {
+            cache,
+            candles_storage,
+            instrument_storage,
+            candle_updates,
+            spreads_cache,
+            spreads_storage,
+            session_calendars,
+        }
+    }
+
+    // empty filter stands for all active instruments
+    async fn get_instruments(&self, instruments: &HashSet<String>) -> Vec<String> {
+        match instruments.is_empty() {
+            true => self.instrument_storage.get_active_instruments().await,
+            false => instruments.iter().cloned().collect(),
+        }
+    }
+
+    async fn get_last_candles_by_side(
+        &self,
+        instrument: &str,
+        side: PriceSide,
+    ) -> Vec<LastCandleGrpc> {
+        let timeframes = self.cache.get_timeframes();
+        let mut result = Vec::with_capacity(timeframes.len());
+
+        for candle_type in timeframes.iter().map(|timeframe| timeframe.candle_type) {
+            let candle = self
+                .cache
+                .get_last_candle(instrument, candle_type, side)
+                .await;
+
+            if let Some(candle) = candle {
+                result.push(LastCandleGrpc {
+                    candle_type: CandleTypeGrpc::from(candle_type) as i32,
+                    candle: Some(candle.into()),
+                });
+            }
+        }
+
+        result
+    }
+
+    async fn get_snapshot(
+        &self,
+        instruments: &HashSet<String>,
+        candle_types: &[CandleType],
+    ) -> Vec<CandleUpdate> {
+        let instruments = self.get_instruments(instruments).await;
+
+        let mut result =
+            Vec::with_capacity(instruments.len() * candle_types.len() * PriceSide::ALL.len());
+
+        for instrument in instruments {
+            for side in PriceSide::ALL {
+                for candle_type in candle_types {
+                    let candle = self
+                        .cache
+                        .get_last_candle(&instrument, *candle_type, side)
+                        .await;
+
+                    if let Some(candle) = candle {
+                        result.push(CandleUpdate {
+                            instrument: instrument.clone(),
+                            side,
+                            candle_type: *candle_type,
+                            candle,
+                        });
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[tonic::async_trait]
+impl CandlesService for CandlesServiceImpl {
+    type SubscribeCandlesStream = ReceiverStream<Result<CandleUpdateGrpc, Status>>;
+
+    #[instrument(skip(self))]
+    async fn get_candles(
+        &self,
+        request: Request<GetCandlesRequest>,
+    ) -> Result<Response<GetCandlesResponse>, Status> {
+        let request = request.into_inner();
+
+        let candle_type: CandleType = CandleTypeGrpc::from_i32(request.candle_type)
+            .ok_or_else(|| Status::invalid_argument("Unknown candle type"))?
+            .into();
+        let side: PriceSide = PriceSideGrpc::from_i32(request.side)
+            .ok_or_else(|| Status::invalid_argument("Unknown price side"))?
+            .into();
+
+        if request.from >= request.to {
+            return Err(Status::invalid_argument("'from' should be less than 'to'"));
+        }
+
+        if !self.instrument_storage.is_active(&request.instrument).await {
+            return Err(Status::not_found(format!(
+                "Unknown instrument: {}",
+                request.instrument
+            )));
+        }
+
+        let mut candles = get_candles_history(
+            &self.cache,
+            self.candles_storage.as_ref(),
+            &request.instrument,
+            candle_type,
+            side,
+            request.from,
+            request.to,
+        )
+        .await;
+
+        if request.fill_gaps {
+            candles = fill_candles_gaps(
+                &self.cache,
+                self.candles_storage.as_ref(),
+                self.session_calendars.get(&request.instrument),
+                &request.instrument,
+                candle_type,
+                side,
+                candles,
+                request.from,
+                request.to,
+            )
+            .await
+            .map_err(Status::invalid_argument)?;
+        }
+
+        let digits = self
+            .instrument_storage
+            .get_digits(&request.instrument)
+            .await;
+
+        let response = GetCandlesResponse {
+            candles: candles.into_iter().map(|candle| candle.into()).collect(),
+            digits: to_grpc_digits(digits),
+        };
+
+        tracing::info!(
+            message = "Sending candles.",
+            instrument = request.instrument,
+            count = response.candles.len()
+        );
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe_candles(
+        &self,
+        request: Request<SubscribeCandlesRequest>,
+    ) -> Result<Response<Self::SubscribeCandlesStream>, Status> {
+        let request = request.into_inner();
+
+        let mut candle_types = Vec::with_capacity(request.candle_types.len());
+        for candle_type in request.candle_types.iter() {
+            let candle_type: CandleType = CandleTypeGrpc::from_i32(*candle_type)
+                .ok_or_else(|| Status::invalid_argument("Unknown candle type"))?
+                .into();
+            candle_types.push(candle_type);
+        }
+
+        if candle_types.is_empty() {
+            candle_types.extend(
+                self.cache
+                    .get_timeframes()
+                    .iter()
+                    .map(|timeframe| timeframe.candle_type),
+            );
+        }
+
+        let instruments: HashSet<String> = request.instruments.into_iter().collect();
+
+        // subscribe before taking the snapshot, so no update falls in between
+        let mut updates = self.candle_updates.subscribe();
+        let snapshot = self.get_snapshot(&instruments, &candle_types).await;
+
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER);
+
+        tokio::spawn(async move {
+            for update in snapshot {
+                if sender.send(Ok(update.into())).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                match updates.recv().await {
+                    Ok(update) => {
+                        if !instruments.is_empty() && !instruments.contains(&update.instrument) {
+                            continue;
+                        }
+
+                        if !candle_types.contains(&update.candle_type) {
+                            continue;
+                        }
+
+                        if sender.send(Ok(update.into())).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // the subscriber has missed updates, it should resubscribe to get a fresh snapshot
+                        tracing::warn!("Candles subscription lagged behind by {} updates", skipped);
+                        let _ = sender
+                            .send(Err(Status::resource_exhausted(format!(
+                                "Subscription lagged behind by {} updates",
+                                skipped
+                            ))))
+                            .await;
+                        return;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_last_candles(
+        &self,
+        request: Request<GetLastCandlesRequest>,
+    ) -> Result<Response<GetLastCandlesResponse>, Status> {
+        let instruments: HashSet<String> = request.into_inner().instruments.into_iter().collect();
+        let instruments = self.get_instruments(&instruments).await;
+
+        let mut response = GetLastCandlesResponse {
+            instruments: Vec::with_capacity(instruments.len()),
+        };
+
+        for instrument in instruments {
+            if !self.instrument_storage.is_active(&instrument).await {
+                continue;
+            }
+
+            let last_price =
+                self.cache
+                    .get_last_price(&instrument)
+                    .await
+                    .map(|price| LastPriceGrpc {
+                        bid: price.bid,
+                        ask: price.ask,
+                        unix_time_sec: price.date,
+                    });
+
+            response.instruments.push(InstrumentLastCandlesGrpc {
+                bid: self
+                    .get_last_candles_by_side(&instrument, PriceSide::Bid)
+                    .await,
+                ask: self
+                    .get_last_candles_by_side(&instrument, PriceSide::Ask)
+                    .await,
+                mid: self
+                    .get_last_candles_by_side(&instrument, PriceSide::Mid)
+                    .await,
+                trade: self
+                    .get_last_candles_by_side(&instrument, PriceSide::Trade)
+                    .await,
+                instrument,
+                last_price,
+            });
+        }
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_instruments(
+        &self,
+        _request: Request<ListInstrumentsRequest>,
+    ) -> Result<Response<ListInstrumentsResponse>, Status> {
+        let mut instruments = Vec::new();
+
+        for (instrument, metadata) in self.instrument_storage.get_all().await {
+            if metadata.retired {
+                continue;
+            }
+
+            let digits = self.instrument_storage.get_digits(&instrument).await;
+            let assets = self
+                .instrument_storage
+                .get_assets(&instrument)
+                .await
+                .unwrap_or_default();
+
+            let mut candle_types: Vec<i32> = metadata
+                .candle_types
+                .into_iter()
+                .map(|candle_type| CandleTypeGrpc::from(candle_type) as i32)
+                .collect();
+            candle_types.sort();
+
+            instruments.push(InstrumentGrpcModel {
+                instrument,
+                first_seen: metadata.first_seen,
+                last_tick: metadata.last_tick,
+                tick_count: metadata.tick_count,
+                candle_types,
+                digits: to_grpc_digits(digits),
+                base_asset: assets.base,
+                quote_asset: assets.quote,
+            });
+        }
+
+        instruments.sort_by(|a, b| a.instrument.cmp(&b.instrument));
+
+        Ok(Response::new(ListInstrumentsResponse { instruments }))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_spread_candles(
+        &self,
+        request: Request<GetSpreadCandlesRequest>,
+    ) -> Result<Response<GetSpreadCandlesResponse>, Status> {
+        let request = request.into_inner();
+
+        let candle_type: CandleType = CandleTypeGrpc::from_i32(request.candle_type)
+            .ok_or_else(|| Status::invalid_argument("Unknown candle type"))?
+            .into();
+
+        if request.from >= request.to {
+            return Err(Status::invalid_argument("'from' should be less than 'to'"));
+        }
+
+        if !self.instrument_storage.is_active(&request.instrument).await {
+            return Err(Status::not_found(format!(
+                "Unknown instrument: {}",
+                request.instrument
+            )));
+        }
+
+        let candles = get_spread_history(
+            &self.spreads_cache,
+            self.spreads_storage.as_ref(),
+            &request.instrument,
+            candle_type,
+            request.from,
+            request.to,
+        )
+        .await;
+
+        Ok(Response::new(GetSpreadCandlesResponse {
+            candles: candles.into_iter().map(|candle| candle.into()).collect(),
+        }))
+    }
+}
+
+fn to_grpc_digits(digits: Option<u32>) -> i32 {
+    digits.map_or(-1, |digits| digits as i32)
+}
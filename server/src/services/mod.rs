@@ -1,3 +1,3 @@
-pub mod example_service;
+pub mod candles_service;
 
-pub use example_service::BookStoreImpl;
+pub use candles_service::CandlesServiceImpl;
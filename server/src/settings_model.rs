@@ -1,4 +1,15 @@
-use serde::{Serialize, Deserialize};
+use std::{collections::HashMap, net::SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::CandlesStorageType,
+    models::{CandleType, DayRolloverSettings, SessionCalendarSettings, Timeframe},
+};
+
+// in-memory limits of the legacy timeframes when the settings have none: a day and a month
+const DEFAULT_MINUTE_LIMIT: usize = 1440;
+const DEFAULT_HOUR_LIMIT: usize = 720;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SettingsModel {
@@ -13,30 +24,145 @@ pub struct SettingsModelInner {
 
     #[serde(rename = "MyNoSqlWriterUrl")]
     pub my_no_sql_writer_url: String,
-    
+
     #[serde(rename = "MyNoSqlReaderHostPort")]
     pub my_no_sql_reader_host_port: String,
 
     #[serde(rename = "SpotServiceBusHostPort")]
     pub spot_service_bus_hos_port: String,
 
-    #[serde(rename = "MinuteLimit")]
-    pub minute_limit: usize,
+    /// Candle types aggregated by the service with their in-memory limits,
+    /// see `get_timeframes` for the set used when it is empty
+    #[serde(rename = "Timeframes", default)]
+    pub timeframes: Vec<Timeframe>,
+
+    /// Legacy in-memory limit of the minute candles, used only without `Timeframes`
+    #[serde(rename = "MinuteLimit", default)]
+    pub minute_limit: Option<usize>,
+
+    /// Legacy in-memory limit of the hour candles, used only without `Timeframes`
+    #[serde(rename = "HourLimit", default)]
+    pub hour_limit: Option<usize>,
+
+    /// How far behind the latest tick of an instrument a tick may arrive before it is dropped
+    #[serde(rename = "TickLatenessSec", default = "default_tick_lateness_sec")]
+    pub tick_lateness_sec: u64,
+
+    /// Rollover time and timezone of the daily candles, UTC midnight when empty
+    #[serde(rename = "DayRollovers", default)]
+    pub day_rollovers: Vec<DayRolloverSettings>,
+
+    /// Price precision of the instruments, persisted prices are rounded to it.
+    /// The digits of the instrument dictionary take precedence.
+    #[serde(rename = "InstrumentDigits", default)]
+    pub instrument_digits: HashMap<String, u32>,
+
+    /// Instrument names of the liquidity providers mapped to the canonical instruments
+    #[serde(rename = "InstrumentAliases", default)]
+    pub instrument_aliases: HashMap<String, String>,
+
+    /// Trading hours of the session based instruments, instruments without one trade around the clock
+    #[serde(rename = "SessionCalendars", default)]
+    pub session_calendars: Vec<SessionCalendarSettings>,
+
+    /// Quotes deviating more from the recent price are rejected, no spike check when empty
+    #[serde(rename = "MaxTickDeviationPercent", default)]
+    pub max_tick_deviation_percent: Option<f64>,
+
+    /// How long the last accepted quote stays the reference of the spike check
+    #[serde(
+        rename = "SpikeRecentPriceSec",
+        default = "default_spike_recent_price_sec"
+    )]
+    pub spike_recent_price_sec: u64,
+
+    /// Publish the rejected quotes to the quarantine topic
+    #[serde(rename = "QuarantineRejectedTicks", default)]
+    pub quarantine_rejected_ticks: bool,
+
+    /// Backend the candles, the spreads and the instrument metadata are persisted to,
+    /// Azure tables by default
+    #[serde(rename = "CandlesStorage", default)]
+    pub candles_storage: CandlesStorageType,
+
+    /// Directory of the segment files of the file storage,
+    /// or the directory of the `candles.db` database of the SQLite storage
+    #[serde(
+        rename = "CandlesStoragePath",
+        default = "default_candles_storage_path"
+    )]
+    pub candles_storage_path: String,
 
-    #[serde(rename = "HourLimit")]
-    pub hour_limit: usize,
+    /// Account of the ask candles, the spreads and the instrument metadata,
+    /// required by the Azure candles storage only
+    #[serde(rename = "AzureStorageAccountAsk", default)]
+    pub azure_storage_account_ask: Option<String>,
 
-    #[serde(rename = "AzureStorageAccountAsk")]
-    pub azure_storage_account_ask: String,
+    #[serde(rename = "AzureStorageAccessKeyAsk", default)]
+    pub azure_storage_access_key_ask: Option<String>,
 
-    #[serde(rename = "AzureStorageAccessKeyAsk")]
-    pub azure_storage_access_key_ask: String,
+    /// Account of the bid candles, required by the Azure candles storage only
+    #[serde(rename = "AzureStorageAccountBid", default)]
+    pub azure_storage_account_bid: Option<String>,
 
-    #[serde(rename = "AzureStorageAccountBid")]
-    pub azure_storage_account_bid: String,
+    #[serde(rename = "AzureStorageAccessKeyBid", default)]
+    pub azure_storage_access_key_bid: Option<String>,
 
-    #[serde(rename = "AzureStorageAccessKeyBid")]
-    pub azure_storage_access_key_bid: String,
+    /// Account of the mid candles, they are kept in the bid account under the MID prefix when empty
+    #[serde(rename = "AzureStorageAccountMid", default)]
+    pub azure_storage_account_mid: Option<String>,
+
+    #[serde(rename = "AzureStorageAccessKeyMid", default)]
+    pub azure_storage_access_key_mid: Option<String>,
+
+    /// Account of the trade candles, they are kept in the ask account under the TRADE prefix when empty
+    #[serde(rename = "AzureStorageAccountTrade", default)]
+    pub azure_storage_account_trade: Option<String>,
+
+    #[serde(rename = "AzureStorageAccessKeyTrade", default)]
+    pub azure_storage_access_key_trade: Option<String>,
+
+    /// Port of the TradingView UDF datafeed and the tick metrics, they are disabled when empty
+    #[serde(rename = "HttpPort", default)]
+    pub http_port: Option<u16>,
+
+    /// Internal address of the admin operations, e.g. 127.0.0.1:8081, they are disabled when empty
+    #[serde(rename = "AdminHttpAddress", default)]
+    pub admin_http_address: Option<SocketAddr>,
+}
+
+fn default_tick_lateness_sec() -> u64 {
+    300
+}
+
+fn default_spike_recent_price_sec() -> u64 {
+    60
+}
+
+fn default_candles_storage_path() -> String {
+    "./candles".to_string()
+}
+
+impl SettingsModelInner {
+    /// Configured timeframes, the legacy minute, hour, day and month set when there are none
+    pub fn get_timeframes(&self) -> Vec<Timeframe> {
+        if !self.timeframes.is_empty() {
+            return self.timeframes.clone();
+        }
+
+        vec![
+            Timeframe::new(
+                CandleType::Minute,
+                Some(self.minute_limit.unwrap_or(DEFAULT_MINUTE_LIMIT)),
+            ),
+            Timeframe::new(
+                CandleType::Hour,
+                Some(self.hour_limit.unwrap_or(DEFAULT_HOUR_LIMIT)),
+            ),
+            Timeframe::new(CandleType::Day, None),
+            Timeframe::new(CandleType::Month, None),
+        ]
+    }
 }
 
 impl rust_service_sdk::app::app_ctx::GetLogStashUrl for SettingsModel {
@@ -4,28 +4,74 @@ use my_service_bus_abstractions::subscriber::{
     MessagesReader, MySbSubscriberHandleError, SubscriberCallback,
 };
 use my_service_bus_tcp_client::MyServiceBusClient;
-use service_candle_writer_generated_proto::{BidAsk, CandleMessage, CandleGroup, CandleItem};
+use service_candle_writer_generated_proto::{
+    BidAsk, CandleGroup, CandleItem, CandleMessage, RejectedBidAsk,
+};
+use tokio::sync::broadcast;
 
 use crate::{
-    caches::CandlesInstrumentsCache,
-    models::{CandlesBidAsk}, domain::InstrumentStorage,
+    caches::{CandleTypeUpdates, CandlesInstrumentsCache, SpreadsCache, TickMetrics},
+    domain::{InstrumentDictionary, InstrumentStorage, TickValidation},
+    models::{
+        CandleModel, CandleType, CandleUpdate, CandlesBidAsk, PriceSide, SessionCalendars,
+        TickRejection,
+    },
 };
 pub struct BidAskSubscriber {
     pub cache: Arc<CandlesInstrumentsCache>,
     pub service_bus: Arc<MyServiceBusClient>,
     pub instrument_storage: Arc<InstrumentStorage>,
+    pub candle_updates: broadcast::Sender<CandleUpdate>,
+    pub spreads_cache: Arc<SpreadsCache>,
+    pub tick_validation: Arc<TickValidation>,
+    pub tick_metrics: Arc<TickMetrics>,
+    pub session_calendars: Arc<SessionCalendars>,
+    pub instrument_dictionary: Arc<InstrumentDictionary>,
 }
 
 impl BidAskSubscriber {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         cache: Arc<CandlesInstrumentsCache>,
         service_bus: Arc<MyServiceBusClient>,
         instrument_storage: Arc<InstrumentStorage>,
+        candle_updates: broadcast::Sender<CandleUpdate>,
+        spreads_cache: Arc<SpreadsCache>,
+        tick_validation: Arc<TickValidation>,
+        tick_metrics: Arc<TickMetrics>,
+        session_calendars: Arc<SessionCalendars>,
+        instrument_dictionary: Arc<InstrumentDictionary>,
     ) -> Self {
         Self {
             cache,
             service_bus,
             instrument_storage,
+            candle_updates,
+            spreads_cache,
+            tick_validation,
+            tick_metrics,
+            session_calendars,
+            instrument_dictionary,
+        }
+    }
+
+    async fn reject(&self, bid_ask: BidAsk, message: &CandlesBidAsk, reason: TickRejection) {
+        tracing::warn!("Rejected bid ask as {}: {:?}", reason.as_str(), message);
+
+        self.tick_metrics
+            .record_rejected_tick(&message.instrument, reason)
+            .await;
+
+        if self.tick_validation.quarantine {
+            let publisher = self.service_bus.get_publisher::<RejectedBidAsk>(true).await;
+            let rejected = RejectedBidAsk {
+                bid_ask: Some(bid_ask),
+                reason: reason.as_str().to_string(),
+            };
+
+            if let Err(err) = publisher.publish(&rejected).await {
+                tracing::error!("Can't publish rejected bid ask: {:?}", err);
+            }
         }
     }
 }
@@ -36,81 +82,170 @@ impl SubscriberCallback<BidAsk> for BidAskSubscriber {
         &self,
         messages_reader: &mut MessagesReader<BidAsk>,
     ) -> Result<(), MySbSubscriberHandleError> {
-
         while let Some(message) = messages_reader.get_next_message() {
-            let message: CandlesBidAsk = message.take_message().into();
+            let bid_ask = message.take_message();
+            let mut message: CandlesBidAsk = bid_ask.clone().into();
+            message.instrument = self
+                .instrument_dictionary
+                .get_canonical(&message.instrument)
+                .to_string();
             let instrument = message.instrument.clone();
             tracing::info!("Handled bid ask: {:?}", message);
-            
-            if !self.instrument_storage.contains(&instrument).await {
-                self.instrument_storage.add(instrument.clone()).await;
+
+            if !self.instrument_dictionary.is_enabled(&instrument).await {
+                self.reject(bid_ask, &message, TickRejection::UnknownInstrument)
+                    .await;
+                continue;
             }
-            
-            let (bid, ask) = self.cache
-            .update_once(message).await;
+
+            let last_price = self.cache.get_last_price(&instrument).await;
+            if let Err(reason) = self.tick_validation.validate(&message, last_price.as_ref()) {
+                self.reject(bid_ask, &message, reason).await;
+                continue;
+            }
+
+            if let Some(calendar) = self.session_calendars.get(&instrument) {
+                if !calendar.is_open(message.date) {
+                    if calendar.drop_out_of_session_ticks {
+                        self.reject(bid_ask, &message, TickRejection::OutOfSession)
+                            .await;
+                        continue;
+                    }
+
+                    tracing::warn!("Bid ask out of the trading session: {:?}", message);
+                    self.tick_metrics
+                        .record_out_of_session_tick(&instrument)
+                        .await;
+                }
+            }
+
+            self.spreads_cache.update(&message).await;
+
+            let date = message.date;
+            let updates = self.cache.update_once(message).await;
+
+            // a late tick updates none of the candles, it is neither recorded nor published
+            if updates.iter().all(|(_, candles)| candles.is_empty()) {
+                continue;
+            }
+
+            self.instrument_storage.record_tick(&instrument, date).await;
 
             let publisher = self.service_bus.get_publisher::<CandleMessage>(true).await;
-            
-            let to_transfer = CandleMessage {
+
+            let mut to_transfer = CandleMessage {
                 instrument: instrument.clone(),
-                unix_time_sec: bid.0.1.datetime,
-                ask: Some(CandleGroup {
-                    minute: Some (CandleItem {
-                        open: ask.0.1.open,
-                        close: ask.0.1.close,
-                        high: ask.0.1.high,
-                        low: ask.0.1.low,
-                    }),
-                    hour: Some (CandleItem {
-                        open: ask.1.1.open,
-                        close: ask.1.1.close,
-                        high: ask.1.1.high,
-                        low: ask.1.1.low,
-                    }),
-                    day: Some (CandleItem {
-                        open: ask.2.1.open,
-                        close: ask.2.1.close,
-                        high: ask.2.1.high,
-                        low: ask.2.1.low,
-                    }),
-                    month: Some (CandleItem {
-                        open: ask.3.1.open,
-                        close: ask.3.1.close,
-                        high: ask.3.1.high,
-                        low: ask.3.1.low,
-                    }),
-                }),
-                bid: Some(CandleGroup {
-                    minute: Some (CandleItem {
-                        open: bid.0.1.open,
-                        close: bid.0.1.close,
-                        high: bid.0.1.high,
-                        low: bid.0.1.low,
-                    }),
-                    hour: Some (CandleItem {
-                        open: bid.1.1.open,
-                        close: bid.1.1.close,
-                        high: bid.1.1.high,
-                        low: bid.1.1.low,
-                    }),
-                    day: Some (CandleItem {
-                        open: bid.2.1.open,
-                        close: bid.2.1.close,
-                        high: bid.2.1.high,
-                        low: bid.2.1.low,
-                    }),
-                    month: Some (CandleItem {
-                        open: bid.3.1.open,
-                        close: bid.3.1.close,
-                        high: bid.3.1.high,
-                        low: bid.3.1.low,
-                    }),
-                }),
+                unix_time_sec: 0,
+                ask: None,
+                bid: None,
+                mid: None,
+                trade: None,
             };
 
+            for (side, candles) in updates.iter() {
+                if candles.is_empty() {
+                    continue;
+                }
+
+                if let Some(unix_time_sec) = get_unix_time_sec(candles) {
+                    to_transfer.unix_time_sec = unix_time_sec;
+                }
+                let group = Some(to_candle_group(candles));
+
+                match side {
+                    PriceSide::Bid => to_transfer.bid = group,
+                    PriceSide::Ask => to_transfer.ask = group,
+                    PriceSide::Mid => to_transfer.mid = group,
+                    PriceSide::Trade => to_transfer.trade = group,
+                }
+            }
+
             publisher.publish(&to_transfer).await.unwrap();
+
+            // no receivers just means there are no live subscriptions at the moment
+            for (side, candles) in updates {
+                for (candle_type, candle) in candles {
+                    let _ = self.candle_updates.send(CandleUpdate {
+                        instrument: instrument.clone(),
+                        side,
+                        candle_type,
+                        candle,
+                    });
+                }
+            }
         }
 
         Ok(())
     }
 }
+
+fn to_candle_item(candle: &CandleModel) -> CandleItem {
+    CandleItem {
+        open: candle.open,
+        close: candle.close,
+        high: candle.high,
+        low: candle.low,
+        ticks: candle.ticks,
+        volume: candle.volume.unwrap_or(0.0),
+        has_volume: candle.volume.is_some(),
+        vwap: candle.vwap,
+    }
+}
+
+/// Start of the shortest updated candle, the minute one unless 1m is not an active timeframe
+pub(crate) fn get_unix_time_sec(candles: &CandleTypeUpdates) -> Option<u64> {
+    candles
+        .iter()
+        .min_by_key(|(candle_type, _)| candle_type.get_max_duration_sec())
+        .map(|(_, candle)| candle.datetime)
+}
+
+pub(crate) fn to_candle_group(candles: &CandleTypeUpdates) -> CandleGroup {
+    let mut group = CandleGroup::default();
+
+    for (candle_type, candle) in candles {
+        let item = Some(to_candle_item(candle));
+
+        match candle_type {
+            CandleType::Minute => group.minute = item,
+            CandleType::Minute5 => group.minute5 = item,
+            CandleType::Minute15 => group.minute15 = item,
+            CandleType::Minute30 => group.minute30 = item,
+            CandleType::Hour => group.hour = item,
+            CandleType::Hour4 => group.hour4 = item,
+            CandleType::Day => group.day = item,
+            CandleType::Week => group.week = item,
+            CandleType::Month => group.month = item,
+        }
+    }
+
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_unix_time_sec;
+    use crate::models::{CandleModel, CandleType};
+
+    #[test]
+    fn test_unix_time_sec_without_minute_candles() {
+        // 2022-09-07 14:08:24 UTC with 5m, 1h and 1d active
+        let candles = vec![
+            (
+                CandleType::Day,
+                CandleModel::new_from_rate(1662508800, 1662559704, 1.1, None),
+            ),
+            (
+                CandleType::Minute5,
+                CandleModel::new_from_rate(1662559500, 1662559704, 1.1, None),
+            ),
+            (
+                CandleType::Hour,
+                CandleModel::new_from_rate(1662559200, 1662559704, 1.1, None),
+            ),
+        ];
+
+        assert_eq!(get_unix_time_sec(&candles), Some(1662559500));
+        assert_eq!(get_unix_time_sec(&Vec::new()), None);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bid_ask_subscriber;
+pub mod trade_subscriber;
 
 pub use bid_ask_subscriber::BidAskSubscriber;
-
+pub use trade_subscriber::TradeSubscriber;
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use my_service_bus_abstractions::subscriber::{
+    MessagesReader, MySbSubscriberHandleError, SubscriberCallback,
+};
+use my_service_bus_tcp_client::MyServiceBusClient;
+use service_candle_writer_generated_proto::{CandleMessage, Trade};
+use tokio::sync::broadcast;
+
+use crate::{
+    caches::CandlesInstrumentsCache,
+    domain::{InstrumentDictionary, InstrumentStorage},
+    models::{CandleUpdate, CandlesTrade, PriceSide},
+};
+
+use super::bid_ask_subscriber::{get_unix_time_sec, to_candle_group};
+
+pub struct TradeSubscriber {
+    pub cache: Arc<CandlesInstrumentsCache>,
+    pub service_bus: Arc<MyServiceBusClient>,
+    pub instrument_storage: Arc<InstrumentStorage>,
+    pub candle_updates: broadcast::Sender<CandleUpdate>,
+    pub instrument_dictionary: Arc<InstrumentDictionary>,
+}
+
+impl TradeSubscriber {
+    pub fn new(
+        cache: Arc<CandlesInstrumentsCache>,
+        service_bus: Arc<MyServiceBusClient>,
+        instrument_storage: Arc<InstrumentStorage>,
+        candle_updates: broadcast::Sender<CandleUpdate>,
+        instrument_dictionary: Arc<InstrumentDictionary>,
+    ) -> Self {
+        Self {
+            cache,
+            service_bus,
+            instrument_storage,
+            candle_updates,
+            instrument_dictionary,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SubscriberCallback<Trade> for TradeSubscriber {
+    async fn handle_messages(
+        &self,
+        messages_reader: &mut MessagesReader<Trade>,
+    ) -> Result<(), MySbSubscriberHandleError> {
+        while let Some(message) = messages_reader.get_next_message() {
+            let mut message: CandlesTrade = message.take_message().into();
+            message.instrument = self
+                .instrument_dictionary
+                .get_canonical(&message.instrument)
+                .to_string();
+            let instrument = message.instrument.clone();
+            tracing::info!("Handled trade: {:?}", message);
+
+            if !self.instrument_dictionary.is_enabled(&instrument).await {
+                tracing::warn!("Skipped trade of an unknown instrument: {:?}", message);
+                continue;
+            }
+
+            let candles = self.cache.update_trade(&message).await;
+
+            // a late trade updates none of the candles, it is neither recorded nor published
+            if candles.is_empty() {
+                continue;
+            }
+
+            self.instrument_storage
+                .record_trade(&instrument, message.date)
+                .await;
+
+            let publisher = self.service_bus.get_publisher::<CandleMessage>(true).await;
+
+            let to_transfer = CandleMessage {
+                instrument: instrument.clone(),
+                unix_time_sec: get_unix_time_sec(&candles).unwrap_or(0),
+                ask: None,
+                bid: None,
+                mid: None,
+                trade: Some(to_candle_group(&candles)),
+            };
+
+            publisher.publish(&to_transfer).await.unwrap();
+
+            // no receivers just means there are no live subscriptions at the moment
+            for (candle_type, candle) in candles {
+                let _ = self.candle_updates.send(CandleUpdate {
+                    instrument: instrument.clone(),
+                    side: PriceSide::Trade,
+                    candle_type,
+                    candle,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}